@@ -1,11 +1,20 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use rtree::core::diff::ChangeCheck;
+use rtree::models::{EntryKind, HashAlgorithm};
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
 #[derive(Parser, Debug)]
 #[command(name = "rtree")]
 #[command(about = "Print a deterministic ASCII directory tree", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Cli {
-    /// Root path to print (defaults to current directory)
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Root path to print (defaults to current directory), or with
+    /// --fromfile the listing to read (defaults to stdin), or with --git-rev
+    /// a directory in the repository
     pub path: Option<PathBuf>,
 
     /// Limit directory traversal to specified depth
@@ -27,4 +36,258 @@ pub struct Cli {
     /// List directories before files
     #[arg(long = "dirsfirst")]
     pub dirs_first: bool,
+
+    /// Show only entries of the given types (comma-separated: f,d,l,p,s,b,c)
+    #[arg(long = "type", value_delimiter = ',', value_parser = parse_entry_kind)]
+    pub types: Vec<EntryKind>,
+
+    /// Omit directories that are empty after filtering
+    #[arg(long = "prune")]
+    pub prune: bool,
+
+    /// Show only files at least this large (bytes, or with K/M/G/T suffix)
+    #[arg(long = "min-size", value_parser = parse_size)]
+    pub min_size: Option<u64>,
+
+    /// Show only files at most this large (bytes, or with K/M/G/T suffix)
+    #[arg(long = "max-size", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// Show only files modified within a duration (e.g. 7d, 12h) or after a reference file
+    #[arg(long = "newer", value_parser = parse_time_bound)]
+    pub newer: Option<SystemTime>,
+
+    /// Show only files modified before a duration ago (e.g. 30d) or before a reference file
+    #[arg(long = "older", value_parser = parse_time_bound)]
+    pub older: Option<SystemTime>,
+
+    /// Do not descend directories that contain more than this many entries
+    #[arg(long = "filelimit")]
+    pub file_limit: Option<usize>,
+
+    /// Show at most this many entries per directory, summarizing the rest
+    #[arg(long = "max-children")]
+    pub max_children: Option<usize>,
+
+    /// Merge directories that contain only a single directory into one line
+    #[arg(long = "compact-dirs")]
+    pub compact_dirs: bool,
+
+    /// When PATH is an archive, also show archives inside it as directories,
+    /// up to 4 levels deep
+    #[arg(long = "nested-archives")]
+    pub nested_archives: bool,
+
+    /// Read newline- or NUL-separated paths from PATH (or stdin) instead of the filesystem
+    #[arg(long = "fromfile")]
+    pub from_file: bool,
+
+    /// Show the tree of a commit, branch or tag of the git repository at PATH
+    /// instead of the working copy
+    #[arg(long = "git-rev", value_name = "REV", conflicts_with = "from_file")]
+    pub git_rev: Option<String>,
+
+    /// Annotate entries with their git status (M, A, ?? or !!), rolled up to
+    /// the directories that contain them
+    #[arg(long = "git-status", conflicts_with_all = ["from_file", "git_rev"])]
+    pub git_status: bool,
+
+    /// Compare two directories, marking entries only in A (-), only in B (+)
+    /// and changed (~), followed by a summary
+    #[arg(
+        long = "diff",
+        num_args = 2,
+        value_names = ["A", "B"],
+        conflicts_with_all = ["path", "from_file", "git_rev", "git_status", "compact_dirs"]
+    )]
+    pub diff: Option<Vec<PathBuf>>,
+
+    /// Save the walk of PATH as a JSON snapshot to FILE instead of printing it
+    #[arg(
+        long = "snapshot",
+        value_name = "FILE",
+        conflicts_with_all = ["from_file", "git_rev", "git_status", "diff", "compact_dirs"]
+    )]
+    pub snapshot: Option<PathBuf>,
+
+    /// Compare PATH, walked with the options saved in the snapshot, against a
+    /// snapshot saved with --snapshot; exits with 0 if nothing changed, 1 if
+    /// something did and 2 on errors
+    #[arg(
+        long = "compare",
+        value_name = "FILE",
+        conflicts_with_all = [
+            "from_file", "git_rev", "git_status", "diff", "compact_dirs", "snapshot",
+            "level", "ignore_pattern", "dirs_only", "types", "prune", "min_size",
+            "max_size", "newer", "older", "file_limit",
+        ]
+    )]
+    pub compare: Option<PathBuf>,
+
+    /// With --diff, how to detect changed files (comma-separated: size, mtime, content)
+    #[arg(
+        long = "changed-by",
+        value_delimiter = ',',
+        value_parser = parse_change_check,
+        requires = "diff"
+    )]
+    pub changed_by: Vec<ChangeCheck>,
+
+    /// Show a content digest before each file (sha256 or blake3); snapshots
+    /// keep the full digest and --compare uses it to find modified files
+    #[arg(
+        long = "hash",
+        value_name = "ALGORITHM",
+        value_parser = parse_hash_algorithm,
+        conflicts_with_all = ["from_file", "git_rev", "diff"]
+    )]
+    pub hash: Option<HashAlgorithm>,
+
+    /// With --hash, also give each directory a digest of its listed children
+    #[arg(long = "hash-dirs", requires = "hash")]
+    pub hash_dirs: bool,
+
+    /// Mark files whose contents match other files' with a group id, and
+    /// report the bytes wasted on copies (compared with --hash's algorithm,
+    /// blake3 by default)
+    #[arg(
+        long = "duplicates",
+        conflicts_with_all = ["from_file", "git_rev", "diff", "snapshot", "compare"]
+    )]
+    pub duplicates: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create the directories and empty files of a layout written in rtree's
+    /// tree format (ASCII or Unicode)
+    Apply(ApplyArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ApplyArgs {
+    /// File containing the layout, or `-` for stdin
+    pub layout: PathBuf,
+
+    /// Directory to create the layout in (defaults to current directory)
+    pub target: Option<PathBuf>,
+
+    /// Print what would be created without touching the filesystem
+    #[arg(short = 'n', long = "dry-run")]
+    pub dry_run: bool,
+}
+
+fn parse_entry_kind(value: &str) -> Result<EntryKind, String> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => EntryKind::from_type_char(c),
+        _ => None,
+    }
+    .ok_or_else(|| format!("invalid type '{value}' (expected one of f, d, l, p, s, b, c)"))
+}
+
+fn parse_change_check(value: &str) -> Result<ChangeCheck, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "size" => Ok(ChangeCheck::Size),
+        "mtime" => Ok(ChangeCheck::Mtime),
+        "content" => Ok(ChangeCheck::Content),
+        _ => Err(format!(
+            "invalid check '{value}' (expected size, mtime or content)"
+        )),
+    }
+}
+
+fn parse_hash_algorithm(value: &str) -> Result<HashAlgorithm, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "sha256" => Ok(HashAlgorithm::Sha256),
+        "blake3" => Ok(HashAlgorithm::Blake3),
+        _ => Err(format!(
+            "invalid algorithm '{value}' (expected sha256 or blake3)"
+        )),
+    }
+}
+
+/// Parse a byte count with an optional binary K/M/G/T suffix (e.g. `512`, `10K`, `1.5M`).
+fn parse_size(value: &str) -> Result<u64, String> {
+    let trimmed = value.trim();
+    let (number, multiplier) = match trimmed.char_indices().last() {
+        Some((index, suffix)) if suffix.is_ascii_alphabetic() => {
+            let multiplier: u64 = match suffix.to_ascii_uppercase() {
+                'B' => 1,
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                'T' => 1 << 40,
+                _ => {
+                    return Err(format!(
+                        "invalid size '{value}' (unknown suffix '{suffix}')"
+                    ));
+                }
+            };
+            (&trimmed[..index], multiplier)
+        }
+        _ => (trimmed, 1),
+    };
+
+    let number: f64 = number
+        .parse()
+        .ok()
+        .filter(|n: &f64| n.is_finite() && *n >= 0.0)
+        .ok_or_else(|| format!("invalid size '{value}'"))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse a duration like `30s`, `15m`, `12h`, `7d` or `2w` into the point in
+/// time that far in the past, or fall back to the modification time of a
+/// reference file.
+fn parse_time_bound(value: &str) -> Result<SystemTime, String> {
+    if let Some(duration) = parse_duration(value) {
+        return SystemTime::now()
+            .checked_sub(duration)
+            .ok_or_else(|| format!("duration '{value}' is too large"));
+    }
+
+    std::fs::metadata(value)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|err| format!("'{value}' is neither a duration nor a readable file: {err}"))
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let unit = value.chars().last()?;
+    let seconds: u64 = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let count: u64 = value[..value.len() - 1].parse().ok()?;
+    Some(Duration::from_secs(count.checked_mul(seconds)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes_with_suffixes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("10K"), Ok(10 * 1024));
+        assert_eq!(parse_size("1.5m"), Ok(1024 * 1024 * 3 / 2));
+        assert_eq!(parse_size("2G"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_size("10Q").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("").is_err());
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("7d"), Some(Duration::from_secs(7 * 86_400)));
+        assert_eq!(parse_duration("2w"), Some(Duration::from_secs(14 * 86_400)));
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("Cargo.toml"), None);
+    }
 }
@@ -0,0 +1,466 @@
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::core::walk::compare_for_display;
+use crate::models::{DirTree, EntryKind, TreeNode};
+
+/// How an entry differs between the old and the new tree.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Change {
+    /// Present in both trees, with no detected change
+    Unchanged,
+    /// Only in the new tree
+    Added,
+    /// Only in the old tree
+    Removed,
+    /// In both trees, but the contents differ
+    Modified,
+}
+
+impl Change {
+    /// The marker shown after a changed entry: `+`, `-` or `~`.
+    pub fn marker(self) -> Option<&'static str> {
+        match self {
+            Change::Unchanged => None,
+            Change::Added => Some("+"),
+            Change::Removed => Some("-"),
+            Change::Modified => Some("~"),
+        }
+    }
+}
+
+/// A way of telling whether a file present in both trees of a diff changed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ChangeCheck {
+    /// Sizes differ
+    Size,
+    /// Modification times differ
+    Mtime,
+    /// Contents differ, compared byte by byte; symlinks compare their targets
+    Content,
+}
+
+/// An entry of the merged tree produced by [`diff_trees`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffNode {
+    pub name: String,
+    pub kind: EntryKind,
+    pub change: Change,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub children: Vec<DiffNode>,
+}
+
+/// Number of added, removed and modified entries, counting every entry
+/// inside an added or removed directory.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+}
+
+impl DiffSummary {
+    /// Returns true if the trees are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.modified == 0
+    }
+}
+
+/// The merged tree of two walks, with every entry marked by its change.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeDiff {
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub children: Vec<DiffNode>,
+    pub summary: DiffSummary,
+}
+
+/// Merge two trees walked with the same options.
+///
+/// Entries are matched by name; one whose kind changed shows up as removed
+/// and added. For non-directories present in both trees, `is_modified` is
+/// called with the path relative to the roots and the old node to decide
+/// whether the contents changed. `dirs_first` must match the walks' sort
+/// order.
+pub fn diff_trees<F: FnMut(&Path, &TreeNode) -> bool>(
+    old: &DirTree,
+    new: &DirTree,
+    dirs_first: bool,
+    mut is_modified: F,
+) -> TreeDiff {
+    let mut summary = DiffSummary::default();
+    let children = diff_children(
+        &old.children,
+        &new.children,
+        &mut DiffState {
+            path: PathBuf::new(),
+            dirs_first,
+            is_modified: &mut is_modified,
+            summary: &mut summary,
+        },
+    );
+    TreeDiff { children, summary }
+}
+
+struct DiffState<'a, F> {
+    path: PathBuf,
+    dirs_first: bool,
+    is_modified: &'a mut F,
+    summary: &'a mut DiffSummary,
+}
+
+fn diff_children<F: FnMut(&Path, &TreeNode) -> bool>(
+    old: &[TreeNode],
+    new: &[TreeNode],
+    state: &mut DiffState<'_, F>,
+) -> Vec<DiffNode> {
+    let mut merged = Vec::with_capacity(old.len().max(new.len()));
+    let (mut old_index, mut new_index) = (0, 0);
+
+    // Both sides are sorted in display order, so merge them like sorted lists
+    while old_index < old.len() || new_index < new.len() {
+        let order = match (old.get(old_index), new.get(new_index)) {
+            (Some(a), Some(b)) => {
+                compare_for_display(&a.name, a.kind, &b.name, b.kind, state.dirs_first)
+            }
+            (Some(_), None) => Ordering::Less,
+            _ => Ordering::Greater,
+        };
+
+        match order {
+            Ordering::Less => {
+                merged.push(one_sided(&old[old_index], Change::Removed, state.summary));
+                old_index += 1;
+            }
+            Ordering::Greater => {
+                merged.push(one_sided(&new[new_index], Change::Added, state.summary));
+                new_index += 1;
+            }
+            Ordering::Equal => {
+                let (a, b) = (&old[old_index], &new[new_index]);
+                old_index += 1;
+                new_index += 1;
+
+                if a.kind != b.kind {
+                    merged.push(one_sided(a, Change::Removed, state.summary));
+                    merged.push(one_sided(b, Change::Added, state.summary));
+                    continue;
+                }
+
+                state.path.push(a.name.trim_end_matches('/'));
+                let (change, children) = if a.kind == EntryKind::Directory {
+                    let children = diff_children(&a.children, &b.children, state);
+                    (Change::Unchanged, children)
+                } else if (state.is_modified)(&state.path, a) {
+                    state.summary.modified += 1;
+                    (Change::Modified, Vec::new())
+                } else {
+                    (Change::Unchanged, Vec::new())
+                };
+                state.path.pop();
+
+                merged.push(DiffNode {
+                    name: b.name.clone(),
+                    kind: b.kind,
+                    change,
+                    children,
+                });
+            }
+        }
+    }
+
+    merged
+}
+
+/// Mark a node and its whole subtree as only present on one side.
+fn one_sided(node: &TreeNode, change: Change, summary: &mut DiffSummary) -> DiffNode {
+    match change {
+        Change::Added => summary.added += 1,
+        Change::Removed => summary.removed += 1,
+        Change::Unchanged | Change::Modified => {}
+    }
+    DiffNode {
+        name: node.name.clone(),
+        kind: node.kind,
+        change,
+        children: node
+            .children
+            .iter()
+            .map(|child| one_sided(child, change, summary))
+            .collect(),
+    }
+}
+
+/// Whether the entries at `old` and `new` on the real filesystem differ by
+/// any of `checks`, for use as the predicate of [`diff_trees`]. Entries that
+/// cannot be read count as changed.
+pub fn is_modified(old: &Path, new: &Path, checks: &[ChangeCheck]) -> bool {
+    checks
+        .iter()
+        .any(|check| check_differs(old, new, *check).unwrap_or(true))
+}
+
+fn check_differs(old: &Path, new: &Path, check: ChangeCheck) -> io::Result<bool> {
+    let old_metadata = std::fs::symlink_metadata(old)?;
+    let new_metadata = std::fs::symlink_metadata(new)?;
+    match check {
+        ChangeCheck::Size => Ok(old_metadata.len() != new_metadata.len()),
+        ChangeCheck::Mtime => Ok(old_metadata.modified()? != new_metadata.modified()?),
+        ChangeCheck::Content if old_metadata.file_type().is_symlink() => {
+            Ok(std::fs::read_link(old)? != std::fs::read_link(new)?)
+        }
+        // FIFOs, sockets and devices have no contents to compare
+        ChangeCheck::Content if !old_metadata.is_file() => Ok(false),
+        ChangeCheck::Content if old_metadata.len() != new_metadata.len() => Ok(true),
+        ChangeCheck::Content => contents_differ(old, new),
+    }
+}
+
+fn contents_differ(old: &Path, new: &Path) -> io::Result<bool> {
+    let mut old = BufReader::new(File::open(old)?);
+    let mut new = BufReader::new(File::open(new)?);
+    loop {
+        let (old_chunk, new_chunk) = (old.fill_buf()?, new.fill_buf()?);
+        if old_chunk.is_empty() || new_chunk.is_empty() {
+            return Ok(old_chunk.len() != new_chunk.len());
+        }
+        let len = old_chunk.len().min(new_chunk.len());
+        if old_chunk[..len] != new_chunk[..len] {
+            return Ok(true);
+        }
+        old.consume(len);
+        new.consume(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn dir(name: &str, children: Vec<TreeNode>) -> TreeNode {
+        TreeNode {
+            name: name.to_owned(),
+            kind: EntryKind::Directory,
+            error: None,
+            exceeded_filelimit: None,
+            status: None,
+            digest: None,
+            duplicate: None,
+            children,
+        }
+    }
+
+    fn leaf(name: &str, kind: EntryKind) -> TreeNode {
+        TreeNode {
+            kind,
+            ..dir(name, vec![])
+        }
+    }
+
+    fn tree(children: Vec<TreeNode>) -> DirTree {
+        DirTree {
+            error: None,
+            exceeded_filelimit: None,
+            children,
+        }
+    }
+
+    fn changes(nodes: &[DiffNode]) -> Vec<(String, Change)> {
+        let mut out = Vec::new();
+        for node in nodes {
+            out.push((node.name.clone(), node.change));
+            for (name, change) in changes(&node.children) {
+                out.push((format!("{}{}", node.name, name), change));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn merges_trees_and_counts_changes() {
+        let old = tree(vec![
+            dir(
+                "bin/",
+                vec![
+                    leaf("app", EntryKind::File),
+                    leaf("old-tool", EntryKind::File),
+                ],
+            ),
+            dir("docs/", vec![leaf("guide.md", EntryKind::File)]),
+            leaf("latest", EntryKind::Symlink),
+        ]);
+        let new = tree(vec![
+            dir(
+                "bin/",
+                vec![leaf("app", EntryKind::File), leaf("tool", EntryKind::File)],
+            ),
+            leaf("latest", EntryKind::File),
+            dir("lib/", vec![leaf("core.so", EntryKind::File)]),
+        ]);
+
+        let mut checked = Vec::new();
+        let diff = diff_trees(&old, &new, false, |path, _| {
+            checked.push(path.to_path_buf());
+            path == Path::new("bin/app")
+        });
+
+        assert_eq!(checked, [PathBuf::from("bin/app")]);
+        assert_eq!(
+            changes(&diff.children),
+            [
+                ("bin/".to_owned(), Change::Unchanged),
+                ("bin/app".to_owned(), Change::Modified),
+                ("bin/old-tool".to_owned(), Change::Removed),
+                ("bin/tool".to_owned(), Change::Added),
+                ("docs/".to_owned(), Change::Removed),
+                ("docs/guide.md".to_owned(), Change::Removed),
+                ("latest".to_owned(), Change::Removed),
+                ("latest".to_owned(), Change::Added),
+                ("lib/".to_owned(), Change::Added),
+                ("lib/core.so".to_owned(), Change::Added),
+            ]
+        );
+        assert_eq!(
+            diff.summary,
+            DiffSummary {
+                added: 4,
+                removed: 4,
+                modified: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn identical_trees_have_an_empty_summary() {
+        let sample = || {
+            tree(vec![
+                dir("src/", vec![leaf("main.rs", EntryKind::File)]),
+                leaf("Cargo.toml", EntryKind::File),
+            ])
+        };
+        let diff = diff_trees(&sample(), &sample(), true, |_, _| false);
+        assert!(diff.summary.is_empty());
+        assert_eq!(diff.children[0].name, "src/");
+        assert!(
+            changes(&diff.children)
+                .iter()
+                .all(|(_, change)| *change == Change::Unchanged)
+        );
+    }
+
+    /// Write each `(name, old, new)` file under `old/` and `new/`.
+    fn file_pairs(files: &[(&str, &str, &str)]) -> tempfile::TempDir {
+        let temp = tempfile::TempDir::new().unwrap();
+        for side in ["old", "new"] {
+            std::fs::create_dir(temp.path().join(side)).unwrap();
+        }
+        for (name, old, new) in files {
+            std::fs::write(temp.path().join("old").join(name), old).unwrap();
+            std::fs::write(temp.path().join("new").join(name), new).unwrap();
+        }
+        temp
+    }
+
+    fn modified(temp: &tempfile::TempDir, name: &str, checks: &[ChangeCheck]) -> bool {
+        let root = temp.path();
+        is_modified(
+            &root.join("old").join(name),
+            &root.join("new").join(name),
+            checks,
+        )
+    }
+
+    #[test]
+    fn size_check_compares_lengths() {
+        let temp = file_pairs(&[("same", "abc", "xyz"), ("longer", "abc", "abcd")]);
+        assert!(!modified(&temp, "same", &[ChangeCheck::Size]));
+        assert!(modified(&temp, "longer", &[ChangeCheck::Size]));
+    }
+
+    #[test]
+    fn mtime_check_compares_modification_times() {
+        let temp = file_pairs(&[("touched", "abc", "abc")]);
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for side in ["old", "new"] {
+            let file = File::options()
+                .write(true)
+                .open(temp.path().join(side).join("touched"))
+                .unwrap();
+            file.set_modified(time).unwrap();
+        }
+        assert!(!modified(&temp, "touched", &[ChangeCheck::Mtime]));
+
+        File::options()
+            .write(true)
+            .open(temp.path().join("new/touched"))
+            .unwrap()
+            .set_modified(time + Duration::from_secs(1))
+            .unwrap();
+        assert!(modified(&temp, "touched", &[ChangeCheck::Mtime]));
+        assert!(!modified(&temp, "touched", &[ChangeCheck::Content]));
+    }
+
+    #[test]
+    fn content_check_compares_bytes() {
+        let temp = file_pairs(&[
+            ("same", "abc", "abc"),
+            ("edited", "abc", "abd"),
+            ("longer", "abc", "abcd"),
+        ]);
+        assert!(!modified(&temp, "same", &[ChangeCheck::Content]));
+        assert!(modified(&temp, "edited", &[ChangeCheck::Content]));
+        assert!(modified(&temp, "longer", &[ChangeCheck::Content]));
+        // Any failing check marks the file
+        assert!(modified(
+            &temp,
+            "edited",
+            &[ChangeCheck::Size, ChangeCheck::Content]
+        ));
+        assert!(!modified(&temp, "edited", &[]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn content_check_compares_symlink_targets() {
+        let temp = file_pairs(&[("a", "abc", "abc"), ("b", "abc", "abc")]);
+        let link = |side: &str, name: &str, target: &str| {
+            std::os::unix::fs::symlink(target, temp.path().join(side).join(name)).unwrap();
+        };
+        link("old", "same", "a");
+        link("new", "same", "a");
+        // Both targets have the same contents, but the links point elsewhere
+        link("old", "moved", "a");
+        link("new", "moved", "b");
+
+        assert!(!modified(&temp, "same", &[ChangeCheck::Content]));
+        assert!(modified(&temp, "moved", &[ChangeCheck::Content]));
+    }
+
+    #[test]
+    fn unreadable_entries_count_as_modified() {
+        let temp = file_pairs(&[("file", "abc", "abc")]);
+        std::fs::remove_file(temp.path().join("new/file")).unwrap();
+        for check in [ChangeCheck::Size, ChangeCheck::Mtime, ChangeCheck::Content] {
+            assert!(modified(&temp, "file", &[check]));
+        }
+
+        // A path that opens but cannot be read as a file
+        std::fs::create_dir(temp.path().join("new/file")).unwrap();
+        assert!(
+            contents_differ(&temp.path().join("old/file"), &temp.path().join("new/file")).is_err()
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+use crate::core::hash::{HashOptions, hash_in_parallel};
+use crate::models::{DirTree, EntryKind, TreeNode};
+
+/// Totals over the duplicate groups found by [`mark_duplicates`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuplicateSummary {
+    /// Sets of files with identical contents
+    pub groups: usize,
+    /// Files in those sets
+    pub files: usize,
+    /// Bytes freed by keeping a single file of each set
+    pub wasted_bytes: u64,
+}
+
+/// Set `duplicate` on every regular file, in a tree walked from `root` on
+/// the real filesystem, whose contents match another listed file's.
+///
+/// Files are grouped by size first, and only those sharing a size with
+/// another file are hashed, in parallel as configured by `options`. Groups
+/// are numbered from 1 in the display order of their first file. Only the
+/// files the walk listed are compared, so its filters and depth limit
+/// apply. Empty files are never duplicates, and files that cannot be read
+/// get an error instead.
+pub fn mark_duplicates(tree: &mut DirTree, root: &Path, options: &HashOptions) -> DuplicateSummary {
+    let mut files = Vec::new();
+    collect_files(&tree.children, &mut root.to_path_buf(), &mut files);
+
+    let mut errors = HashMap::new();
+    let mut by_size: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+    for (index, path) in files.iter().enumerate() {
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.len() > 0 => {
+                by_size.entry(metadata.len()).or_default().push(index);
+            }
+            Ok(_) => {}
+            Err(err) => {
+                errors.insert(index, Error::io(path.clone(), &err));
+            }
+        }
+    }
+
+    // A file with a unique size cannot have a duplicate, so it is never read
+    let candidates: Vec<(usize, u64)> = by_size
+        .into_iter()
+        .filter(|(_, indices)| indices.len() > 1)
+        .flat_map(|(size, indices)| indices.into_iter().map(move |index| (index, size)))
+        .collect();
+    let jobs: Vec<(PathBuf, EntryKind)> = candidates
+        .iter()
+        .map(|&(index, _)| (files[index].clone(), EntryKind::File))
+        .collect();
+
+    let mut by_content: HashMap<(u64, String), Vec<usize>> = HashMap::new();
+    for (&(index, size), result) in candidates.iter().zip(hash_in_parallel(&jobs, options)) {
+        match result {
+            Ok(hex) => by_content.entry((size, hex)).or_default().push(index),
+            Err(err) => {
+                errors.insert(index, Error::io(files[index].clone(), &err));
+            }
+        }
+    }
+
+    let mut groups: Vec<(u64, Vec<usize>)> = by_content
+        .into_iter()
+        .filter(|(_, indices)| indices.len() > 1)
+        .map(|((size, _), indices)| (size, indices))
+        .collect();
+    groups.sort_by_key(|(_, indices)| indices[0]);
+
+    let mut summary = DuplicateSummary::default();
+    let mut group_of = HashMap::new();
+    for (id, (size, indices)) in groups.iter().enumerate() {
+        summary.groups += 1;
+        summary.files += indices.len();
+        summary.wasted_bytes += size * (indices.len() as u64 - 1);
+        group_of.extend(indices.iter().map(|&index| (index, id + 1)));
+    }
+
+    assign_children(&mut tree.children, &mut 0, &group_of, &mut errors);
+    summary
+}
+
+/// Whether the entry is compared with the others.
+fn is_candidate(node: &TreeNode) -> bool {
+    node.error.is_none() && node.kind == EntryKind::File
+}
+
+/// Collect the paths of candidate files in depth-first order, the order
+/// `assign_children` numbers them in.
+fn collect_files(children: &[TreeNode], path: &mut PathBuf, files: &mut Vec<PathBuf>) {
+    for node in children {
+        path.push(node.name.trim_end_matches('/'));
+        if is_candidate(node) {
+            files.push(path.clone());
+        }
+        collect_files(&node.children, path, files);
+        path.pop();
+    }
+}
+
+fn assign_children(
+    children: &mut [TreeNode],
+    next: &mut usize,
+    group_of: &HashMap<usize, usize>,
+    errors: &mut HashMap<usize, Error>,
+) {
+    for node in children.iter_mut() {
+        if is_candidate(node) {
+            node.duplicate = group_of.get(next).copied();
+            node.error = errors.remove(next);
+            *next += 1;
+        }
+        assign_children(&mut node.children, next, group_of, errors);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::walk::{WalkOptions, walk_dir_blocking};
+    use crate::fs::RealFileSystem;
+
+    fn groups(tree: &DirTree) -> Vec<(String, usize)> {
+        tree.iter_depth_first()
+            .filter_map(|item| Some((item.path.display().to_string(), item.node.duplicate?)))
+            .collect()
+    }
+
+    #[test]
+    fn groups_files_with_equal_contents() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        std::fs::create_dir_all(root.join("icons/old")).unwrap();
+        for (path, contents) in [
+            ("icons/logo.png", "logo"),
+            ("icons/old/logo.png", "logo"),
+            ("icons/old/logo-v1.png", "LOGO"),
+            ("banner.png", "banner"),
+            ("banner-copy.png", "banner"),
+            ("copy-of-logo.png", "logo"),
+            ("empty-a", ""),
+            ("empty-b", ""),
+        ] {
+            std::fs::write(root.join(path), contents).unwrap();
+        }
+
+        let mut tree =
+            walk_dir_blocking(&RealFileSystem::new(), root, &WalkOptions::default()).unwrap();
+        let summary = mark_duplicates(&mut tree, root, &HashOptions::default());
+
+        assert_eq!(
+            groups(&tree),
+            [
+                ("banner-copy.png".to_owned(), 1),
+                ("banner.png".to_owned(), 1),
+                ("copy-of-logo.png".to_owned(), 2),
+                ("icons/logo.png".to_owned(), 2),
+                ("icons/old/logo.png".to_owned(), 2),
+            ]
+        );
+        assert_eq!(
+            summary,
+            DuplicateSummary {
+                groups: 2,
+                files: 5,
+                wasted_bytes: 6 + 2 * 4,
+            }
+        );
+    }
+
+    #[test]
+    fn only_compares_listed_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        std::fs::create_dir(root.join("cache")).unwrap();
+        std::fs::write(root.join("a.bin"), "data").unwrap();
+        std::fs::write(root.join("cache/a.bin"), "data").unwrap();
+
+        let options = WalkOptions {
+            ignore_pattern: Some("cache".to_owned()),
+            ..WalkOptions::default()
+        };
+        let mut tree = walk_dir_blocking(&RealFileSystem::new(), root, &options).unwrap();
+        let summary = mark_duplicates(&mut tree, root, &HashOptions::default());
+
+        assert!(groups(&tree).is_empty());
+        assert_eq!(summary, DuplicateSummary::default());
+    }
+}
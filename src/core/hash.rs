@@ -0,0 +1,325 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use sha2::Digest as _;
+
+use crate::Error;
+use crate::models::{Digest, DirTree, EntryKind, HashAlgorithm, TreeNode};
+
+/// Options for [`hash_tree`].
+#[derive(Clone, Debug, Default)]
+pub struct HashOptions {
+    pub algorithm: HashAlgorithm,
+    /// Also give each directory a digest of its listed children
+    pub directories: bool,
+    /// Number of files read at once (None means one per available CPU)
+    pub threads: Option<NonZeroUsize>,
+}
+
+/// Set the `digest` of every file and symlink in a tree walked from `root`
+/// on the real filesystem, reading files in parallel. A symlink's digest is
+/// that of its target path; FIFOs, sockets and devices get none.
+///
+/// Entries that cannot be read get an error instead. With
+/// `options.directories`, each directory gets a Merkle-style digest over the
+/// kind, name and digest of its listed children in name order, so it only
+/// covers what the walk's filters and depth limit kept, and is the same
+/// whichever way the children are sorted. Directories with an unreadable
+/// entry anywhere below them get no digest.
+pub fn hash_tree(tree: &mut DirTree, root: &Path, options: &HashOptions) {
+    let mut jobs = Vec::new();
+    collect_jobs(&tree.children, &mut root.to_path_buf(), &mut jobs);
+
+    let mut results = hash_in_parallel(&jobs, options).into_iter();
+    assign_children(
+        &mut tree.children,
+        &mut root.to_path_buf(),
+        &mut results,
+        options,
+    );
+}
+
+/// Whether the entry's contents are hashed.
+fn is_hashed(node: &TreeNode) -> bool {
+    node.error.is_none() && matches!(node.kind, EntryKind::File | EntryKind::Symlink)
+}
+
+/// Collect the paths to hash in depth-first order, the order
+/// `assign_children` hands the results back out in.
+fn collect_jobs(children: &[TreeNode], path: &mut PathBuf, jobs: &mut Vec<(PathBuf, EntryKind)>) {
+    for node in children {
+        path.push(node.name.trim_end_matches('/'));
+        if is_hashed(node) {
+            jobs.push((path.clone(), node.kind));
+        }
+        collect_jobs(&node.children, path, jobs);
+        path.pop();
+    }
+}
+
+/// Hash each file or symlink in `jobs` on `options.threads` threads,
+/// returning the hex digests in the same order.
+pub(crate) fn hash_in_parallel(
+    jobs: &[(PathBuf, EntryKind)],
+    options: &HashOptions,
+) -> Vec<io::Result<String>> {
+    let (algorithm, threads) = (options.algorithm, thread_count(options));
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<io::Result<String>>> = jobs.iter().map(|_| None).collect();
+
+    // Workers claim jobs one at a time, so a few large files do not leave
+    // the other threads idle
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.clamp(1, jobs.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((path, kind)) = jobs.get(index) else {
+                            return done;
+                        };
+                        done.push((index, hash_entry(path, *kind, algorithm)));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            for (index, result) in worker.join().expect("hash worker panicked") {
+                results[index] = Some(result);
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.expect("every job is hashed"))
+        .collect()
+}
+
+fn thread_count(options: &HashOptions) -> usize {
+    options
+        .threads
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+}
+
+fn hash_entry(path: &Path, kind: EntryKind, algorithm: HashAlgorithm) -> io::Result<String> {
+    let mut hasher = Hasher::new(algorithm);
+    if kind == EntryKind::Symlink {
+        hasher.write_all(std::fs::read_link(path)?.as_os_str().as_encoded_bytes())?;
+    } else {
+        io::copy(&mut File::open(path)?, &mut hasher)?;
+    }
+    Ok(hasher.finalize_hex())
+}
+
+/// Store the results and, bottom up, the directory digests. Returns whether
+/// every entry was read.
+fn assign_children<I: Iterator<Item = io::Result<String>>>(
+    children: &mut [TreeNode],
+    path: &mut PathBuf,
+    results: &mut I,
+    options: &HashOptions,
+) -> bool {
+    let mut complete = true;
+    for node in children.iter_mut() {
+        path.push(node.name.trim_end_matches('/'));
+        if is_hashed(node) {
+            match results.next().expect("a result per job") {
+                Ok(hex) => {
+                    node.digest = Some(Digest {
+                        algorithm: options.algorithm,
+                        hex,
+                    });
+                }
+                Err(err) => node.error = Some(Error::io(path.clone(), &err)),
+            }
+        }
+        let children_complete = assign_children(&mut node.children, path, results, options);
+        path.pop();
+
+        let node_complete =
+            children_complete && node.error.is_none() && node.exceeded_filelimit.is_none();
+        if options.directories && node.kind == EntryKind::Directory && node_complete {
+            node.digest = Some(directory_digest(&node.children, options.algorithm));
+        }
+        complete &= node_complete;
+    }
+    complete
+}
+
+fn directory_digest(children: &[TreeNode], algorithm: HashAlgorithm) -> Digest {
+    let mut sorted: Vec<&TreeNode> = children.iter().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut hasher = Hasher::new(algorithm);
+    for node in sorted {
+        let hex = node
+            .digest
+            .as_ref()
+            .map_or("", |digest| digest.hex.as_str());
+        let name = node.name.trim_end_matches('/');
+        let tag = node.kind.type_char().unwrap_or('?');
+        hasher.update(format!("{tag} {name}\0{hex}\n").as_bytes());
+    }
+    Digest {
+        algorithm,
+        hex: hasher.finalize_hex(),
+    }
+}
+
+enum Hasher {
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(bytes),
+            Hasher::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        match self {
+            Hasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::walk::{WalkOptions, walk_dir_blocking};
+    use crate::fs::RealFileSystem;
+
+    fn hashed(root: &Path, walk: &WalkOptions, options: &HashOptions) -> DirTree {
+        let mut tree = walk_dir_blocking(&RealFileSystem::new(), root, walk).unwrap();
+        hash_tree(&mut tree, root, options);
+        tree
+    }
+
+    fn digest_of<'a>(tree: &'a DirTree, path: &str) -> Option<&'a str> {
+        tree.get(Path::new(path))
+            .and_then(|node| node.digest.as_ref())
+            .map(|digest| digest.hex.as_str())
+    }
+
+    #[test]
+    fn hashes_files_with_either_algorithm() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp.path().join("abc.txt"), "abc").unwrap();
+
+        let sha256 = hashed(
+            temp.path(),
+            &WalkOptions::default(),
+            &HashOptions::default(),
+        );
+        assert_eq!(
+            digest_of(&sha256, "abc.txt"),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+
+        let blake3 = HashOptions {
+            algorithm: HashAlgorithm::Blake3,
+            threads: NonZeroUsize::new(1),
+            ..HashOptions::default()
+        };
+        let blake3 = hashed(temp.path(), &WalkOptions::default(), &blake3);
+        assert_eq!(
+            digest_of(&blake3, "abc.txt"),
+            Some("6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85")
+        );
+        assert_eq!(
+            blake3.children[0].digest.as_ref().unwrap().short().len(),
+            12
+        );
+    }
+
+    #[test]
+    fn directory_digests_follow_contents_not_sort_order() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        std::fs::create_dir_all(root.join("pkg/lib")).unwrap();
+        std::fs::write(root.join("pkg/a.txt"), "one").unwrap();
+        std::fs::write(root.join("pkg/lib/b.txt"), "two").unwrap();
+        let options = HashOptions {
+            directories: true,
+            ..HashOptions::default()
+        };
+
+        let before = hashed(root, &WalkOptions::default(), &options);
+        let dirs_first = WalkOptions {
+            dirs_first: true,
+            ..WalkOptions::default()
+        };
+        let sorted = hashed(root, &dirs_first, &options);
+        assert!(digest_of(&before, "pkg").is_some());
+        assert_eq!(digest_of(&before, "pkg"), digest_of(&sorted, "pkg"));
+
+        std::fs::write(root.join("pkg/lib/b.txt"), "three").unwrap();
+        let after = hashed(root, &WalkOptions::default(), &options);
+        assert_eq!(
+            digest_of(&before, "pkg/a.txt"),
+            digest_of(&after, "pkg/a.txt")
+        );
+        assert_ne!(digest_of(&before, "pkg/lib"), digest_of(&after, "pkg/lib"));
+        assert_ne!(digest_of(&before, "pkg"), digest_of(&after, "pkg"));
+    }
+
+    #[test]
+    fn unreadable_entries_get_errors_and_no_directory_digest() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp.path().join("pkg")).unwrap();
+        std::fs::write(temp.path().join("pkg/gone.txt"), "").unwrap();
+        std::fs::write(temp.path().join("kept.txt"), "").unwrap();
+        let mut tree =
+            walk_dir_blocking(&RealFileSystem::new(), temp.path(), &WalkOptions::default())
+                .unwrap();
+        std::fs::remove_file(temp.path().join("pkg/gone.txt")).unwrap();
+
+        let options = HashOptions {
+            directories: true,
+            ..HashOptions::default()
+        };
+        hash_tree(&mut tree, temp.path(), &options);
+
+        let gone = tree.get(Path::new("pkg/gone.txt")).unwrap();
+        assert!(matches!(
+            gone.error,
+            Some(Error::Io {
+                kind: io::ErrorKind::NotFound,
+                ..
+            })
+        ));
+        assert!(gone.digest.is_none());
+        assert!(digest_of(&tree, "pkg").is_none());
+        assert!(digest_of(&tree, "kept.txt").is_some());
+    }
+}
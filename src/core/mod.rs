@@ -1,2 +1,13 @@
+pub mod diff;
+#[cfg(feature = "hash")]
+pub mod duplicates;
+#[cfg(feature = "hash")]
+pub mod hash;
+pub mod parse;
+pub mod query;
 pub mod render;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod transform;
+pub mod visit;
 pub mod walk;
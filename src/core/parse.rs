@@ -0,0 +1,465 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::models::{DirTree, EntryKind, TreeNode};
+use crate::{Error, Result};
+
+/// Branches that introduce an entry, in the ASCII and Unicode charsets
+const BRANCHES: &[&str] = &["|-- ", "`-- ", "├── ", "└── "];
+/// Indentation, one unit per ancestor level
+const INDENTS: &[&str] = &["|   ", "│   ", "    "];
+/// Git status and diff markers the renderer appends to names
+const MARKERS: &[&str] = &["M", "A", "??", "!!", "+", "-", "~"];
+
+/// Parse tree-formatted text, as written by `write_children` and optionally
+/// preceded by the root line, back into a `DirTree`.
+///
+/// Both the ASCII charset and tree(1)'s Unicode line-drawing charset are
+/// accepted. Names ending in `/` are directories, as is any entry listed
+/// with children; everything else is a file. A name with several components,
+/// as written by `--compact-dirs`, becomes nested directories. Annotations
+/// the renderer adds (digests, errors, file limits, status, duplicate and
+/// diff markers) are dropped. Children keep the order they are listed in,
+/// and a name listed twice in the same directory is an error.
+pub fn parse_tree(text: &str) -> Result<DirTree> {
+    let mut children = Vec::new();
+    // The most recent entry at each depth, whose children are still being read
+    let mut open: Vec<TreeNode> = Vec::new();
+    let mut first_line = true;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        // tree(1) pads its Unicode charset with non-breaking spaces
+        let line = line.replace('\u{a0}', " ");
+        let line = line.trim_end();
+        if line.chars().all(|c| matches!(c, ' ' | '|' | '│')) {
+            continue;
+        }
+
+        let is_root_line = std::mem::take(&mut first_line);
+        let Some((depth, name)) = split_line(line) else {
+            if is_root_line {
+                continue;
+            }
+            return Err(invalid(line_number, "expected an entry such as `|-- name`"));
+        };
+        if depth > open.len() {
+            return Err(invalid(line_number, "indented deeper than its parent"));
+        }
+
+        close_to(&mut open, &mut children, depth);
+        let node = parse_entry(name).map_err(|message| invalid(line_number, &message))?;
+        let siblings = match open.last() {
+            Some(parent) => &parent.children,
+            None => &children,
+        };
+        let first = node.name.split('/').next().unwrap_or_default();
+        if siblings
+            .iter()
+            .any(|sibling| sibling.name.trim_end_matches('/') == first)
+        {
+            return Err(invalid(line_number, &format!("duplicate entry `{first}`")));
+        }
+        open.push(node);
+    }
+    close_to(&mut open, &mut children, 0);
+
+    Ok(DirTree {
+        error: None,
+        exceeded_filelimit: None,
+        children,
+    })
+}
+
+/// Split a line into its depth and the text after the branch.
+fn split_line(line: &str) -> Option<(usize, &str)> {
+    let mut rest = line;
+    let mut depth = 0;
+    loop {
+        if let Some(name) = BRANCHES.iter().find_map(|branch| rest.strip_prefix(branch)) {
+            return Some((depth, name));
+        }
+        rest = INDENTS
+            .iter()
+            .find_map(|indent| rest.strip_prefix(indent))?;
+        depth += 1;
+    }
+}
+
+fn parse_entry(text: &str) -> std::result::Result<TreeNode, String> {
+    let mut name = strip_digest(text);
+    while let Some(stripped) = strip_annotation(name) {
+        name = stripped;
+    }
+
+    if name.starts_with("... ")
+        && (name.ends_with(" more entries") || name.ends_with(" more entry"))
+    {
+        return Err("elided entries cannot be recreated".to_owned());
+    }
+    let trimmed = name.trim_end_matches('/');
+    let valid = !trimmed.is_empty()
+        && Path::new(trimmed)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !valid {
+        return Err(format!("invalid name `{name}`"));
+    }
+
+    let (name, kind) = if name.ends_with('/') {
+        (format!("{trimmed}/"), EntryKind::Directory)
+    } else {
+        (name.to_owned(), EntryKind::File)
+    };
+    Ok(TreeNode {
+        name,
+        kind,
+        error: None,
+        exceeded_filelimit: None,
+        status: None,
+        digest: None,
+        duplicate: None,
+        children: Vec::new(),
+    })
+}
+
+/// Remove the `[digest] ` prefix written for hashed trees, if there is one.
+fn strip_digest(text: &str) -> &str {
+    let Some((digest, rest)) = text
+        .strip_prefix('[')
+        .and_then(|text| text.split_once("] "))
+    else {
+        return text;
+    };
+    let is_hex = !digest.is_empty() && digest.bytes().all(|byte| byte.is_ascii_hexdigit());
+    if is_hex { rest } else { text }
+}
+
+/// Remove one trailing annotation written by the renderer, if there is one.
+fn strip_annotation(name: &str) -> Option<&str> {
+    let (rest, annotation) = name.strip_suffix(']')?.rsplit_once(" [")?;
+    let known = MARKERS.contains(&annotation)
+        || annotation
+            .strip_prefix("dup #")
+            .is_some_and(|group| group.parse::<usize>().is_ok())
+        || annotation.starts_with("error: ")
+        || annotation.ends_with(" entries exceeds filelimit, not opening dir");
+    known.then_some(rest)
+}
+
+/// Attach open entries deeper than `depth` to their parents.
+fn close_to(open: &mut Vec<TreeNode>, children: &mut Vec<TreeNode>, depth: usize) {
+    while open.len() > depth {
+        let node = expand(open.pop().expect("open entry"));
+        match open.last_mut() {
+            Some(parent) => {
+                // Listed with children, so it is a directory even without a `/`
+                if parent.kind != EntryKind::Directory {
+                    parent.kind = EntryKind::Directory;
+                    parent.name.push('/');
+                }
+                parent.children.push(node);
+            }
+            None => children.push(node),
+        }
+    }
+}
+
+/// Turn a multi-component name such as `src/main/java/` into nested nodes.
+fn expand(mut node: TreeNode) -> TreeNode {
+    let Some((parents, last)) = node.name.trim_end_matches('/').rsplit_once('/') else {
+        return node;
+    };
+    let parents: Vec<String> = parents
+        .split('/')
+        .filter(|name| !name.is_empty())
+        .map(|name| format!("{name}/"))
+        .collect();
+    let last = last.to_owned();
+    node.name = match node.kind {
+        EntryKind::Directory => format!("{last}/"),
+        _ => last,
+    };
+
+    parents
+        .into_iter()
+        .rev()
+        .fold(node, |child, name| TreeNode {
+            name,
+            kind: EntryKind::Directory,
+            error: None,
+            exceeded_filelimit: None,
+            status: None,
+            digest: None,
+            duplicate: None,
+            children: vec![child],
+        })
+}
+
+/// An entry of a layout and what [`materialize`] did with it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Action {
+    /// Path relative to the target directory
+    pub path: PathBuf,
+    /// `Directory` or `File`
+    pub kind: EntryKind,
+    /// Whether the entry was already there and left alone; otherwise it was
+    /// created, or with a dry run would have been
+    pub exists: bool,
+}
+
+/// Create the directories and empty files of a parsed layout under `target`,
+/// creating `target` itself if needed and leaving existing entries alone.
+///
+/// Every entry is checked before anything is created, so a conflict, such
+/// as a file where the layout has a directory or a path listed twice, fails
+/// without touching the filesystem. With `dry_run`, nothing is created either way. Returns one
+/// action per entry, in depth-first order.
+pub fn materialize(tree: &DirTree, target: &Path, dry_run: bool) -> Result<Vec<Action>> {
+    let mut actions = Vec::new();
+    let mut planned = HashSet::new();
+    for item in tree.iter_depth_first() {
+        if !planned.insert(item.path.clone()) {
+            return Err(Error::InvalidTree {
+                message: format!("duplicate entry `{}`", item.path.display()),
+            });
+        }
+        let path = target.join(&item.path);
+        let is_dir = item.node.kind == EntryKind::Directory;
+        let exists = match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_dir() == is_dir => true,
+            Ok(_) => {
+                let message = if is_dir {
+                    "exists and is not a directory"
+                } else {
+                    "exists and is a directory"
+                };
+                return Err(Error::io(
+                    path,
+                    &io::Error::new(io::ErrorKind::AlreadyExists, message),
+                ));
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+            Err(err) => return Err(Error::io(path, &err)),
+        };
+        actions.push(Action {
+            path: item.path,
+            kind: item.node.kind,
+            exists,
+        });
+    }
+
+    if !dry_run {
+        std::fs::create_dir_all(target).map_err(|err| Error::io(target, &err))?;
+        // Parents come before their children in depth-first order
+        for action in actions.iter().filter(|action| !action.exists) {
+            let path = target.join(&action.path);
+            let created = if action.kind == EntryKind::Directory {
+                std::fs::create_dir(&path)
+            } else {
+                File::create_new(&path).map(drop)
+            };
+            created.map_err(|err| Error::io(path, &err))?;
+        }
+    }
+
+    Ok(actions)
+}
+
+fn invalid(line_number: usize, message: &str) -> Error {
+    Error::InvalidTree {
+        message: format!("line {line_number}: {message}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::render::{write_children, write_root};
+    use crate::core::walk::{WalkOptions, walk_dir_blocking};
+    use crate::fs::MemoryFileSystem;
+
+    fn names(tree: &DirTree) -> Vec<String> {
+        tree.iter_depth_first()
+            .map(|item| format!("{}{}", "  ".repeat(item.depth), item.node.name))
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_rendered_output() {
+        let fs = MemoryFileSystem::from_paths(
+            "/project",
+            [
+                "Cargo.toml",
+                "src/main.rs",
+                "src/core/walk.rs",
+                "docs/",
+                "tests/cli.rs",
+            ],
+        );
+        let tree = walk_dir_blocking(&fs, Path::new("/project"), &WalkOptions::default()).unwrap();
+        let mut text = Vec::new();
+        write_root(&mut text, "project", None, None).unwrap();
+        write_children(&mut text, &tree.children).unwrap();
+
+        let parsed = parse_tree(std::str::from_utf8(&text).unwrap()).unwrap();
+        assert_eq!(parsed, tree);
+    }
+
+    #[test]
+    fn accepts_unicode_compacted_and_annotated_entries() {
+        let text = concat!(
+            "├── [0123456789ab] README.md [M]\n",
+            "├── bin\n",
+            "│\u{a0}\u{a0} └── run [+] [dup #2]\n",
+            "│\n",
+            "└── src/main/java/ [error: Permission denied]\n",
+            "    └── App.java\n",
+        );
+
+        let tree = parse_tree(text).unwrap();
+        assert_eq!(
+            names(&tree),
+            [
+                "README.md",
+                "bin/",
+                "  run",
+                "src/",
+                "  main/",
+                "    java/",
+                "      App.java",
+            ]
+        );
+        assert_eq!(tree.children[0].kind, EntryKind::File);
+        assert_eq!(tree.children[1].kind, EntryKind::Directory);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let error = |text: &str| match parse_tree(text) {
+            Err(Error::InvalidTree { message }) => message,
+            other => panic!("expected an invalid tree, got {other:?}"),
+        };
+
+        assert_eq!(
+            error("root\n|-- a/\n|       `-- b\n"),
+            "line 3: indented deeper than its parent"
+        );
+        assert_eq!(
+            error("|-- a\nstray\n"),
+            "line 2: expected an entry such as `|-- name`"
+        );
+        assert_eq!(error("`-- ../escape\n"), "line 1: invalid name `../escape`");
+        assert_eq!(
+            error("|-- a\n`-- ... 3 more entries\n"),
+            "line 2: elided entries cannot be recreated"
+        );
+    }
+
+    fn layout() -> DirTree {
+        parse_tree("project\n|-- Cargo.toml\n`-- src/\n    `-- main.rs\n").unwrap()
+    }
+
+    fn summary(actions: &[Action]) -> Vec<String> {
+        actions
+            .iter()
+            .map(|action| {
+                let verb = if action.exists { "exists" } else { "create" };
+                format!("{verb} {}", action.path.display())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn materializes_missing_entries() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let target = temp.path().join("project");
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(target.join("Cargo.toml"), "[package]").unwrap();
+
+        let actions = materialize(&layout(), &target, false).unwrap();
+        assert_eq!(
+            summary(&actions),
+            ["exists Cargo.toml", "create src", "create src/main.rs"]
+        );
+        assert_eq!(actions[1].kind, EntryKind::Directory);
+        assert_eq!(
+            std::fs::read_to_string(target.join("Cargo.toml")).unwrap(),
+            "[package]"
+        );
+        assert!(target.join("src/main.rs").is_file());
+
+        let again = materialize(&layout(), &target, false).unwrap();
+        assert!(again.iter().all(|action| action.exists));
+    }
+
+    #[test]
+    fn dry_run_reports_without_creating() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let target = temp.path().join("project");
+
+        let actions = materialize(&layout(), &target, true).unwrap();
+        assert_eq!(
+            summary(&actions),
+            ["create Cargo.toml", "create src", "create src/main.rs"]
+        );
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn conflicts_fail_before_creating_anything() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let target = temp.path();
+        std::fs::write(target.join("src"), "").unwrap();
+
+        for dry_run in [true, false] {
+            let err = materialize(&layout(), target, dry_run).unwrap_err();
+            assert_eq!(err.io_kind(), Some(io::ErrorKind::AlreadyExists));
+            assert_eq!(err.path(), Some(target.join("src").as_path()));
+            assert_eq!(err.to_string(), "exists and is not a directory");
+        }
+        assert!(!target.join("Cargo.toml").exists());
+
+        std::fs::remove_file(target.join("src")).unwrap();
+        std::fs::create_dir(target.join("Cargo.toml")).unwrap();
+        let err = materialize(&layout(), target, false).unwrap_err();
+        assert_eq!(err.to_string(), "exists and is a directory");
+        assert!(!target.join("src").exists());
+    }
+
+    #[test]
+    fn rejects_names_listed_twice_in_a_directory() {
+        for layout in [
+            "|-- a\n`-- a/\n",
+            "`-- src/\n    |-- main.rs\n    `-- main.rs [M]\n",
+            "|-- src/main.rs\n`-- src/lib.rs\n",
+        ] {
+            let err = parse_tree(layout).unwrap_err();
+            assert!(err.to_string().contains("duplicate entry"), "{layout}");
+        }
+        assert_eq!(
+            parse_tree("|-- a\n`-- a/\n").unwrap_err().to_string(),
+            "Invalid tree: line 2: duplicate entry `a`"
+        );
+        // The same name in different directories is fine
+        assert!(parse_tree("|-- a/\n|   `-- a\n`-- b/\n    `-- a\n").is_ok());
+    }
+
+    #[test]
+    fn repeated_paths_fail_before_creating_anything() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut tree = layout();
+        let file = tree.children[0].clone();
+        tree.children.push(TreeNode {
+            name: "Cargo.toml/".to_owned(),
+            kind: EntryKind::Directory,
+            ..file
+        });
+
+        let err = materialize(&tree, temp.path(), false).unwrap_err();
+        assert!(err.to_string().contains("duplicate entry `Cargo.toml`"));
+        assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Component, Path, PathBuf};
+
+use crate::Result;
+use crate::core::walk::CompiledPatterns;
+use crate::models::{DirTree, EntryKind, TreeNode};
+
+/// A node yielded by tree iteration, with its location in the tree.
+#[derive(Clone, Debug)]
+pub struct TreeItem<'a> {
+    /// Path relative to the node iteration started from
+    pub path: PathBuf,
+    pub node: &'a TreeNode,
+    /// Nesting level, where the top-level children are at depth 0
+    pub depth: usize,
+}
+
+/// Pre-order, depth-first iteration in display order.
+pub struct DepthFirst<'a> {
+    stack: Vec<TreeItem<'a>>,
+}
+
+impl<'a> DepthFirst<'a> {
+    fn new(children: &'a [TreeNode]) -> Self {
+        let mut stack = Vec::new();
+        push_children(&mut stack, children, Path::new(""), 0);
+        stack.reverse();
+        DepthFirst { stack }
+    }
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = TreeItem<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.stack.pop()?;
+        let start = self.stack.len();
+        push_children(
+            &mut self.stack,
+            &item.node.children,
+            &item.path,
+            item.depth + 1,
+        );
+        // Pushed in display order, so flip them to pop the first child next
+        self.stack[start..].reverse();
+        Some(item)
+    }
+}
+
+/// Level-by-level iteration; each level is in display order.
+pub struct BreadthFirst<'a> {
+    queue: VecDeque<TreeItem<'a>>,
+}
+
+impl<'a> BreadthFirst<'a> {
+    fn new(children: &'a [TreeNode]) -> Self {
+        let mut queue = VecDeque::new();
+        push_children(&mut queue, children, Path::new(""), 0);
+        BreadthFirst { queue }
+    }
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = TreeItem<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.queue.pop_front()?;
+        push_children(
+            &mut self.queue,
+            &item.node.children,
+            &item.path,
+            item.depth + 1,
+        );
+        Some(item)
+    }
+}
+
+fn push_children<'a>(
+    items: &mut impl Extend<TreeItem<'a>>,
+    children: &'a [TreeNode],
+    parent: &Path,
+    depth: usize,
+) {
+    items.extend(children.iter().map(|node| TreeItem {
+        path: parent.join(node.name.trim_end_matches('/')),
+        node,
+        depth,
+    }));
+}
+
+impl DirTree {
+    /// Iterate over every node depth-first, in display order.
+    pub fn iter_depth_first(&self) -> DepthFirst<'_> {
+        DepthFirst::new(&self.children)
+    }
+
+    /// Iterate over every node breadth-first.
+    pub fn iter_breadth_first(&self) -> BreadthFirst<'_> {
+        BreadthFirst::new(&self.children)
+    }
+
+    /// Look up a node by its path relative to the root, e.g. `src/main.rs`.
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&TreeNode> {
+        lookup(&self.children, path.as_ref())
+    }
+
+    /// Find nodes matching a pipe-separated glob pattern, in display order.
+    ///
+    /// As with `-I`, a pattern is matched against the node's name without the
+    /// trailing `/`. A pattern containing `/` is matched against the whole
+    /// relative path instead, where `*` may also span `/`.
+    pub fn find(&self, pattern: &str) -> Result<Vec<TreeItem<'_>>> {
+        find_in(self.iter_depth_first(), pattern)
+    }
+
+    /// Count the nodes of each kind in the tree.
+    pub fn count_by_kind(&self) -> HashMap<EntryKind, usize> {
+        count_by_kind(self.iter_depth_first())
+    }
+}
+
+impl TreeNode {
+    /// Iterate over this node's descendants depth-first, with paths relative
+    /// to this node.
+    pub fn iter_depth_first(&self) -> DepthFirst<'_> {
+        DepthFirst::new(&self.children)
+    }
+
+    /// Iterate over this node's descendants breadth-first, with paths
+    /// relative to this node.
+    pub fn iter_breadth_first(&self) -> BreadthFirst<'_> {
+        BreadthFirst::new(&self.children)
+    }
+
+    /// Look up a descendant by its path relative to this node.
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&TreeNode> {
+        lookup(&self.children, path.as_ref())
+    }
+
+    /// Find descendants matching a pipe-separated glob pattern.
+    pub fn find(&self, pattern: &str) -> Result<Vec<TreeItem<'_>>> {
+        find_in(self.iter_depth_first(), pattern)
+    }
+
+    /// Count this node's descendants by kind.
+    pub fn count_by_kind(&self) -> HashMap<EntryKind, usize> {
+        count_by_kind(self.iter_depth_first())
+    }
+}
+
+/// Resolve `path` one component at a time. A compacted node such as
+/// `src/main/` consumes as many components as its name has.
+fn lookup<'a>(children: &'a [TreeNode], path: &Path) -> Option<&'a TreeNode> {
+    let components: Vec<Component<'_>> = path
+        .components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect();
+    let mut children = children;
+    let mut remaining = &components[..];
+    let mut found = None;
+
+    while !remaining.is_empty() {
+        let (node, consumed) = children.iter().find_map(|node| {
+            let name: Vec<Component<'_>> = Path::new(node.name.trim_end_matches('/'))
+                .components()
+                .collect();
+            remaining.starts_with(&name).then_some((node, name.len()))
+        })?;
+        remaining = &remaining[consumed..];
+        children = &node.children;
+        found = Some(node);
+    }
+
+    found
+}
+
+fn find_in<'a>(
+    items: impl Iterator<Item = TreeItem<'a>>,
+    pattern: &str,
+) -> Result<Vec<TreeItem<'a>>> {
+    let patterns = CompiledPatterns::new(pattern)?;
+    let match_path = pattern.contains('/');
+
+    Ok(items
+        .filter(|item| {
+            if match_path {
+                patterns.matches(&item.path.to_string_lossy())
+            } else {
+                patterns.matches(item.node.name.trim_end_matches('/'))
+            }
+        })
+        .collect())
+}
+
+fn count_by_kind<'a>(items: impl Iterator<Item = TreeItem<'a>>) -> HashMap<EntryKind, usize> {
+    let mut counts = HashMap::new();
+    for item in items {
+        *counts.entry(item.node.kind).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::transform::compact_dirs;
+
+    fn dir(name: &str, children: Vec<TreeNode>) -> TreeNode {
+        TreeNode {
+            name: name.to_owned(),
+            kind: EntryKind::Directory,
+            error: None,
+            exceeded_filelimit: None,
+            status: None,
+            digest: None,
+            duplicate: None,
+            children,
+        }
+    }
+
+    fn leaf(name: &str, kind: EntryKind) -> TreeNode {
+        TreeNode {
+            name: name.to_owned(),
+            kind,
+            error: None,
+            exceeded_filelimit: None,
+            status: None,
+            digest: None,
+            duplicate: None,
+            children: vec![],
+        }
+    }
+
+    fn fixture() -> DirTree {
+        DirTree {
+            error: None,
+            exceeded_filelimit: None,
+            children: vec![
+                dir(
+                    "src/",
+                    vec![
+                        dir("core/", vec![leaf("walk.rs", EntryKind::File)]),
+                        leaf("lib.rs", EntryKind::File),
+                    ],
+                ),
+                leaf("Cargo.toml", EntryKind::File),
+                leaf("link", EntryKind::Symlink),
+            ],
+        }
+    }
+
+    fn paths<'a>(items: impl Iterator<Item = TreeItem<'a>>) -> Vec<String> {
+        items
+            .map(|item| format!("{} {}", item.path.display(), item.depth))
+            .collect()
+    }
+
+    #[test]
+    fn depth_first_follows_display_order() {
+        let tree = fixture();
+        assert_eq!(
+            paths(tree.iter_depth_first()),
+            [
+                "src 0",
+                "src/core 1",
+                "src/core/walk.rs 2",
+                "src/lib.rs 1",
+                "Cargo.toml 0",
+                "link 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn breadth_first_visits_level_by_level() {
+        let tree = fixture();
+        assert_eq!(
+            paths(tree.iter_breadth_first()),
+            [
+                "src 0",
+                "Cargo.toml 0",
+                "link 0",
+                "src/core 1",
+                "src/lib.rs 1",
+                "src/core/walk.rs 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn get_resolves_relative_paths() {
+        let tree = fixture();
+        assert_eq!(tree.get("src/core/walk.rs").unwrap().name, "walk.rs");
+        assert_eq!(tree.get("./src/core").unwrap().name, "core/");
+        assert!(tree.get("src/missing").is_none());
+        assert!(tree.get("").is_none());
+
+        let src = tree.get("src").unwrap();
+        assert_eq!(src.get("lib.rs").unwrap().name, "lib.rs");
+    }
+
+    #[test]
+    fn get_walks_through_compacted_directories() {
+        let mut tree = DirTree {
+            error: None,
+            exceeded_filelimit: None,
+            children: vec![dir(
+                "a/",
+                vec![dir("b/", vec![leaf("c.txt", EntryKind::File)])],
+            )],
+        };
+        compact_dirs(&mut tree);
+        assert_eq!(tree.get("a/b/c.txt").unwrap().name, "c.txt");
+        assert_eq!(tree.get("a/b").unwrap().name, "a/b/");
+        assert!(tree.get("a").is_none());
+    }
+
+    #[test]
+    fn find_matches_names_or_paths() {
+        let tree = fixture();
+        let found = |pattern: &str| -> Vec<String> {
+            tree.find(pattern)
+                .unwrap()
+                .iter()
+                .map(|item| item.path.display().to_string())
+                .collect()
+        };
+
+        assert_eq!(found("*.rs"), ["src/core/walk.rs", "src/lib.rs"]);
+        assert_eq!(found("core|link"), ["src/core", "link"]);
+        assert_eq!(found("src/*.rs"), ["src/core/walk.rs", "src/lib.rs"]);
+        assert_eq!(found("src/?ib.rs"), ["src/lib.rs"]);
+    }
+
+    #[test]
+    fn counts_nodes_by_kind() {
+        let tree = fixture();
+        let counts = tree.count_by_kind();
+        assert_eq!(counts[&EntryKind::Directory], 2);
+        assert_eq!(counts[&EntryKind::File], 3);
+        assert_eq!(counts[&EntryKind::Symlink], 1);
+        assert!(!counts.contains_key(&EntryKind::Fifo));
+
+        let src = tree.get("src").unwrap();
+        assert_eq!(src.count_by_kind()[&EntryKind::File], 2);
+    }
+}
@@ -1,55 +1,352 @@
+use std::borrow::Cow;
 use std::io::{self, Write};
 
-use crate::models::TreeNode;
+use crate::Error;
+use crate::core::diff::{DiffNode, DiffSummary};
+#[cfg(feature = "hash")]
+use crate::core::duplicates::DuplicateSummary;
+#[cfg(feature = "async")]
+use crate::core::walk::{DirWalker, WalkEvent};
+#[cfg(feature = "async")]
+use crate::fs::FileSystem;
+use crate::models::{EntryKind, TreeNode};
+
+/// Configuration options for rendering a tree.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Maximum children shown per directory; the rest are summarized in a
+    /// trailing "... N more entries" line (None means unlimited)
+    pub max_children: Option<usize>,
+}
+
+/// Write the root line: the display name followed by any annotations.
+pub fn write_root<W: Write>(
+    writer: &mut W,
+    name: &str,
+    error: Option<&Error>,
+    exceeded_filelimit: Option<usize>,
+) -> io::Result<()> {
+    writer.write_all(name.as_bytes())?;
+    write_annotations(writer, error, exceeded_filelimit)?;
+    writer.write_all(b"\n")
+}
 
 pub fn write_children<W: Write>(writer: &mut W, children: &[TreeNode]) -> io::Result<()> {
-    write_children_inner(writer, children, &[])
+    write_children_with_options(writer, children, &RenderOptions::default())
+}
+
+/// Render children with the given options.
+pub fn write_children_with_options<W: Write>(
+    writer: &mut W,
+    children: &[TreeNode],
+    options: &RenderOptions,
+) -> io::Result<()> {
+    write_children_inner(writer, children, options, &[])
 }
 
 fn write_children_inner<W: Write>(
     writer: &mut W,
     children: &[TreeNode],
+    options: &RenderOptions,
     ancestor_has_more: &[bool],
 ) -> io::Result<()> {
-    for (index, node) in children.iter().enumerate() {
-        let is_last = index + 1 == children.len();
-
-        for &has_more in ancestor_has_more {
-            if has_more {
-                writer.write_all(b"|   ")?;
-            } else {
-                writer.write_all(b"    ")?;
-            }
-        }
+    let shown = match options.max_children {
+        Some(max) => children.len().min(max),
+        None => children.len(),
+    };
+    let elided = children.len() - shown;
 
-        if is_last {
-            writer.write_all(b"`-- ")?;
-        } else {
-            writer.write_all(b"|-- ")?;
+    for (index, node) in children[..shown].iter().enumerate() {
+        let is_last = index + 1 == shown && elided == 0;
+        let name = decorated_name(&node.name, node.kind);
+        // Digests go in front of the name, like tree(1)'s size and mode columns
+        let label = match &node.digest {
+            Some(digest) => Cow::Owned(format!("[{}] {name}", digest.short())),
+            None => name,
+        };
+
+        let mut markers = Vec::new();
+        if let Some(status) = node.status {
+            markers.push(Cow::Borrowed(status.marker()));
+        }
+        if let Some(group) = node.duplicate {
+            markers.push(Cow::Owned(format!("dup #{group}")));
         }
 
-        writer.write_all(node.name.as_bytes())?;
+        write_line(
+            writer,
+            ancestor_has_more,
+            is_last,
+            &label,
+            &markers,
+            node.error.as_ref(),
+            node.exceeded_filelimit,
+        )?;
 
-        if let Some(error) = node.error.as_ref() {
-            write!(writer, " [error: {error}]")?;
+        if !node.children.is_empty() {
+            let mut next_ancestor_has_more = ancestor_has_more.to_vec();
+            next_ancestor_has_more.push(!is_last);
+            write_children_inner(writer, &node.children, options, &next_ancestor_has_more)?;
         }
+    }
+
+    if elided > 0 {
+        write_elided(writer, ancestor_has_more, elided)?;
+    }
+
+    Ok(())
+}
+
+/// Render the merged tree of a diff, marking added (`+`), removed (`-`) and
+/// modified (`~`) entries.
+pub fn write_diff_children<W: Write>(
+    writer: &mut W,
+    children: &[DiffNode],
+    options: &RenderOptions,
+) -> io::Result<()> {
+    write_diff_inner(writer, children, options, &[])
+}
+
+fn write_diff_inner<W: Write>(
+    writer: &mut W,
+    children: &[DiffNode],
+    options: &RenderOptions,
+    ancestor_has_more: &[bool],
+) -> io::Result<()> {
+    let shown = match options.max_children {
+        Some(max) => children.len().min(max),
+        None => children.len(),
+    };
+    let elided = children.len() - shown;
+
+    for (index, node) in children[..shown].iter().enumerate() {
+        let is_last = index + 1 == shown && elided == 0;
 
+        write_prefix(writer, ancestor_has_more, is_last)?;
+        writer.write_all(decorated_name(&node.name, node.kind).as_bytes())?;
+        if let Some(marker) = node.change.marker() {
+            write!(writer, " [{marker}]")?;
+        }
         writer.write_all(b"\n")?;
 
         if !node.children.is_empty() {
             let mut next_ancestor_has_more = ancestor_has_more.to_vec();
             next_ancestor_has_more.push(!is_last);
-            write_children_inner(writer, &node.children, &next_ancestor_has_more)?;
+            write_diff_inner(writer, &node.children, options, &next_ancestor_has_more)?;
         }
     }
 
+    if elided > 0 {
+        write_elided(writer, ancestor_has_more, elided)?;
+    }
+
     Ok(())
 }
 
+/// Write the closing summary of a diff, e.g. `2 added, 1 removed, 0 modified`.
+pub fn write_diff_summary<W: Write>(writer: &mut W, summary: &DiffSummary) -> io::Result<()> {
+    writeln!(
+        writer,
+        "\n{} added, {} removed, {} modified",
+        summary.added, summary.removed, summary.modified
+    )
+}
+
+/// Write the closing summary of a duplicate search, e.g.
+/// `2 duplicate groups, 5 files, 14 bytes wasted`.
+#[cfg(feature = "hash")]
+pub fn write_duplicate_summary<W: Write>(
+    writer: &mut W,
+    summary: &DuplicateSummary,
+) -> io::Result<()> {
+    let group = if summary.groups == 1 {
+        "group"
+    } else {
+        "groups"
+    };
+    let byte = if summary.wasted_bytes == 1 {
+        "byte"
+    } else {
+        "bytes"
+    };
+    writeln!(
+        writer,
+        "\n{} duplicate {group}, {} files, {} {byte} wasted",
+        summary.groups, summary.files, summary.wasted_bytes
+    )
+}
+
+/// Render a streaming walk incrementally, producing the same output as
+/// [`write_children_with_options`] on the equivalent `DirTree`.
+///
+/// Siblings elided by `max_children` are skipped in the walker, so their
+/// subtrees are never read.
+#[cfg(feature = "async")]
+pub async fn write_walk<W: Write, F: FileSystem>(
+    writer: &mut W,
+    walker: &mut DirWalker<'_, F>,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let mut ancestor_has_more: Vec<bool> = Vec::new();
+    // One flag per open directory: elide the siblings after the current entry
+    let mut elide_after_current = vec![false];
+
+    if options.max_children == Some(0) {
+        finish_level_early(writer, walker, &ancestor_has_more)?;
+    }
+
+    while let Some(event) = walker.next().await {
+        let (entry, descended) = match event {
+            WalkEvent::Entry(entry) => (entry, false),
+            WalkEvent::EnterDir(entry) => (entry, true),
+            // Errors are already carried inline by the entry they belong to
+            WalkEvent::Error(_) => continue,
+            WalkEvent::LeaveDir => {
+                ancestor_has_more.pop();
+                elide_after_current.pop();
+                finish_entry(writer, walker, &ancestor_has_more, &mut elide_after_current)?;
+                continue;
+            }
+        };
+
+        let elide_rest = options
+            .max_children
+            .is_some_and(|max| entry.index + 1 == max && entry.sibling_count > max);
+        write_line(
+            writer,
+            &ancestor_has_more,
+            entry.is_last,
+            &decorated_name(&entry.name, entry.kind),
+            &[],
+            entry.error.as_ref(),
+            entry.exceeded_filelimit,
+        )?;
+        *elide_after_current.last_mut().expect("open level") = elide_rest;
+
+        if descended {
+            ancestor_has_more.push(!entry.is_last);
+            elide_after_current.push(false);
+            if options.max_children == Some(0) {
+                finish_level_early(writer, walker, &ancestor_has_more)?;
+            }
+        } else {
+            finish_entry(writer, walker, &ancestor_has_more, &mut elide_after_current)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Called once an entry and its subtree are written; emits the elision
+/// marker if the remaining siblings are to be skipped.
+#[cfg(feature = "async")]
+fn finish_entry<W: Write, F: FileSystem>(
+    writer: &mut W,
+    walker: &mut DirWalker<'_, F>,
+    ancestor_has_more: &[bool],
+    elide_after_current: &mut [bool],
+) -> io::Result<()> {
+    match elide_after_current.last_mut() {
+        Some(elide) if *elide => {
+            *elide = false;
+            finish_level_early(writer, walker, ancestor_has_more)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(feature = "async")]
+fn finish_level_early<W: Write, F: FileSystem>(
+    writer: &mut W,
+    walker: &mut DirWalker<'_, F>,
+    ancestor_has_more: &[bool],
+) -> io::Result<()> {
+    let skipped = walker.skip_remaining_siblings();
+    if skipped > 0 {
+        write_elided(writer, ancestor_has_more, skipped)?;
+    }
+    Ok(())
+}
+
+/// The name followed by its kind's type indicator, if it has one.
+fn decorated_name(name: &str, kind: EntryKind) -> Cow<'_, str> {
+    match kind.indicator() {
+        Some(indicator) => Cow::Owned(format!("{name}{indicator}")),
+        None => Cow::Borrowed(name),
+    }
+}
+
+fn write_line<W: Write>(
+    writer: &mut W,
+    ancestor_has_more: &[bool],
+    is_last: bool,
+    name: &str,
+    markers: &[Cow<'_, str>],
+    error: Option<&Error>,
+    exceeded_filelimit: Option<usize>,
+) -> io::Result<()> {
+    write_prefix(writer, ancestor_has_more, is_last)?;
+    writer.write_all(name.as_bytes())?;
+    for marker in markers {
+        write!(writer, " [{marker}]")?;
+    }
+    write_annotations(writer, error, exceeded_filelimit)?;
+    writer.write_all(b"\n")
+}
+
+fn write_annotations<W: Write>(
+    writer: &mut W,
+    error: Option<&Error>,
+    exceeded_filelimit: Option<usize>,
+) -> io::Result<()> {
+    if let Some(error) = error {
+        write!(writer, " [error: {error}]")?;
+    }
+
+    if let Some(count) = exceeded_filelimit {
+        write!(
+            writer,
+            " [{count} entries exceeds filelimit, not opening dir]"
+        )?;
+    }
+
+    Ok(())
+}
+
+fn write_elided<W: Write>(
+    writer: &mut W,
+    ancestor_has_more: &[bool],
+    elided: usize,
+) -> io::Result<()> {
+    write_prefix(writer, ancestor_has_more, true)?;
+    let noun = if elided == 1 { "entry" } else { "entries" };
+    writeln!(writer, "... {elided} more {noun}")
+}
+
+fn write_prefix<W: Write>(
+    writer: &mut W,
+    ancestor_has_more: &[bool],
+    is_last: bool,
+) -> io::Result<()> {
+    for &has_more in ancestor_has_more {
+        if has_more {
+            writer.write_all(b"|   ")?;
+        } else {
+            writer.write_all(b"    ")?;
+        }
+    }
+
+    if is_last {
+        writer.write_all(b"`-- ")
+    } else {
+        writer.write_all(b"|-- ")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{EntryKind, TreeNode};
+    use crate::models::{Digest, EntryKind, GitStatus, HashAlgorithm, TreeNode};
 
     #[test]
     fn renders_scaffold_and_errors() {
@@ -58,22 +355,42 @@ mod tests {
                 name: "a".to_owned(),
                 kind: EntryKind::File,
                 error: None,
+                exceeded_filelimit: None,
+                status: None,
+                digest: None,
+                duplicate: None,
                 children: vec![],
             },
             TreeNode {
                 name: "b/".to_owned(),
                 kind: EntryKind::Directory,
-                error: Some("Permission denied".to_owned()),
+                error: Some(Error::Io {
+                    path: "b".into(),
+                    kind: std::io::ErrorKind::PermissionDenied,
+                    message: "Permission denied".to_owned(),
+                }),
+                exceeded_filelimit: None,
+                status: None,
+                digest: None,
+                duplicate: None,
                 children: vec![],
             },
             TreeNode {
                 name: "c/".to_owned(),
                 kind: EntryKind::Directory,
                 error: None,
+                exceeded_filelimit: None,
+                status: None,
+                digest: None,
+                duplicate: None,
                 children: vec![TreeNode {
                     name: "d".to_owned(),
                     kind: EntryKind::File,
                     error: None,
+                    exceeded_filelimit: None,
+                    status: None,
+                    digest: None,
+                    duplicate: None,
                     children: vec![],
                 }],
             },
@@ -93,4 +410,367 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn renders_filelimit_annotation() {
+        let children = vec![TreeNode {
+            name: "cache/".to_owned(),
+            kind: EntryKind::Directory,
+            error: None,
+            exceeded_filelimit: Some(12034),
+            status: None,
+            digest: None,
+            duplicate: None,
+            children: vec![],
+        }];
+
+        let mut out = Vec::new();
+        write_children(&mut out, &children).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(
+            out,
+            "`-- cache/ [12034 entries exceeds filelimit, not opening dir]\n"
+        );
+    }
+
+    #[test]
+    fn renders_diff_markers_and_summary() {
+        use crate::core::diff::Change;
+        use crate::models::EntryKind;
+
+        let node = |name: &str, kind, change, children| DiffNode {
+            name: name.to_owned(),
+            kind,
+            change,
+            children,
+        };
+        let children = vec![
+            node(
+                "bin/",
+                EntryKind::Directory,
+                Change::Unchanged,
+                vec![
+                    node("app", EntryKind::File, Change::Modified, vec![]),
+                    node("tool", EntryKind::File, Change::Unchanged, vec![]),
+                ],
+            ),
+            node(
+                "docs/",
+                EntryKind::Directory,
+                Change::Removed,
+                vec![node("guide.md", EntryKind::File, Change::Removed, vec![])],
+            ),
+            node("NEWS", EntryKind::File, Change::Added, vec![]),
+        ];
+
+        let mut out = Vec::new();
+        write_diff_children(&mut out, &children, &RenderOptions::default()).unwrap();
+        write_diff_summary(
+            &mut out,
+            &DiffSummary {
+                added: 1,
+                removed: 2,
+                modified: 1,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "|-- bin/\n",
+                "|   |-- app [~]\n",
+                "|   `-- tool\n",
+                "|-- docs/ [-]\n",
+                "|   `-- guide.md [-]\n",
+                "`-- NEWS [+]\n",
+                "\n",
+                "1 added, 2 removed, 1 modified\n",
+            )
+        );
+    }
+
+    #[test]
+    fn renders_git_status_markers() {
+        let node = |name: &str, kind, status, children| TreeNode {
+            name: name.to_owned(),
+            kind,
+            error: None,
+            exceeded_filelimit: None,
+            status,
+            digest: None,
+            duplicate: None,
+            children,
+        };
+        let children = vec![
+            node(
+                "notes.txt",
+                EntryKind::File,
+                Some(GitStatus::Untracked),
+                vec![],
+            ),
+            node(
+                "src/",
+                EntryKind::Directory,
+                Some(GitStatus::Modified),
+                vec![
+                    node("lib.rs", EntryKind::File, None, vec![]),
+                    node(
+                        "main.rs",
+                        EntryKind::File,
+                        Some(GitStatus::Modified),
+                        vec![],
+                    ),
+                ],
+            ),
+            node(
+                "target/",
+                EntryKind::Directory,
+                Some(GitStatus::Ignored),
+                vec![],
+            ),
+        ];
+
+        let mut out = Vec::new();
+        write_children(&mut out, &children).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "|-- notes.txt [??]\n",
+                "|-- src/ [M]\n",
+                "|   |-- lib.rs\n",
+                "|   `-- main.rs [M]\n",
+                "`-- target/ [!!]\n",
+            )
+        );
+    }
+
+    #[test]
+    fn renders_truncated_digests_before_names() {
+        let digest = |hex: &str| {
+            Some(Digest {
+                algorithm: HashAlgorithm::Sha256,
+                hex: hex.to_owned(),
+            })
+        };
+        let children = vec![TreeNode {
+            name: "bin/".to_owned(),
+            kind: EntryKind::Directory,
+            error: None,
+            exceeded_filelimit: None,
+            status: None,
+            digest: digest("0123456789abcdef0123"),
+            duplicate: None,
+            children: vec![TreeNode {
+                name: "app".to_owned(),
+                kind: EntryKind::File,
+                error: None,
+                exceeded_filelimit: None,
+                status: Some(GitStatus::Added),
+                digest: digest("fedcba9876543210fedc"),
+                duplicate: None,
+                children: vec![],
+            }],
+        }];
+
+        let mut out = Vec::new();
+        write_children(&mut out, &children).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "`-- [0123456789ab] bin/\n",
+                "    `-- [fedcba987654] app [A]\n",
+            )
+        );
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn renders_duplicate_groups_and_summary() {
+        let file = |name: &str, status, duplicate| TreeNode {
+            name: name.to_owned(),
+            kind: EntryKind::File,
+            error: None,
+            exceeded_filelimit: None,
+            status,
+            digest: None,
+            duplicate,
+            children: vec![],
+        };
+        let children = vec![
+            file("a.png", None, Some(1)),
+            file("b.png", Some(GitStatus::Untracked), Some(1)),
+            file("c.png", None, None),
+        ];
+
+        let mut out = Vec::new();
+        write_children(&mut out, &children).unwrap();
+        write_duplicate_summary(
+            &mut out,
+            &DuplicateSummary {
+                groups: 1,
+                files: 2,
+                wasted_bytes: 512,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "|-- a.png [dup #1]\n",
+                "|-- b.png [??] [dup #1]\n",
+                "`-- c.png\n",
+                "\n",
+                "1 duplicate group, 2 files, 512 bytes wasted\n",
+            )
+        );
+    }
+
+    #[test]
+    fn renders_type_indicators_for_fifos_and_sockets() {
+        let node = |name: &str, kind| TreeNode {
+            name: name.to_owned(),
+            kind,
+            error: None,
+            exceeded_filelimit: None,
+            status: None,
+            digest: None,
+            duplicate: None,
+            children: vec![],
+        };
+        let children = vec![
+            node("app.sock", EntryKind::Socket),
+            node("events", EntryKind::Fifo),
+            node("null", EntryKind::CharDevice),
+            node("sda", EntryKind::BlockDevice),
+        ];
+
+        let mut out = Vec::new();
+        write_children(&mut out, &children).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "|-- app.sock=\n",
+                "|-- events|\n",
+                "|-- null\n",
+                "`-- sda\n",
+            )
+        );
+    }
+
+    #[test]
+    fn truncates_children_with_more_entries_marker() {
+        let file = |name: &str| TreeNode {
+            name: name.to_owned(),
+            kind: EntryKind::File,
+            error: None,
+            exceeded_filelimit: None,
+            status: None,
+            digest: None,
+            duplicate: None,
+            children: vec![],
+        };
+        let children = vec![
+            TreeNode {
+                name: "big/".to_owned(),
+                kind: EntryKind::Directory,
+                error: None,
+                exceeded_filelimit: None,
+                status: None,
+                digest: None,
+                duplicate: None,
+                children: vec![file("1"), file("2"), file("3"), file("4")],
+            },
+            file("x"),
+            file("y"),
+        ];
+
+        let options = RenderOptions {
+            max_children: Some(2),
+        };
+        let mut out = Vec::new();
+        write_children_with_options(&mut out, &children, &options).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(
+            out,
+            concat!(
+                "|-- big/\n",
+                "|   |-- 1\n",
+                "|   |-- 2\n",
+                "|   `-- ... 2 more entries\n",
+                "|-- x\n",
+                "`-- ... 1 more entry\n",
+            )
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn streaming_render_matches_tree_render() {
+        use crate::core::walk::{WalkOptions, walk_dir, walk_dir_streaming};
+        use crate::fs::MockFileSystem;
+        use crate::models::FsEntry;
+        use std::path::{Path, PathBuf};
+
+        let entry = |path: &str, kind: EntryKind| FsEntry {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap().to_owned(),
+            kind,
+            metadata: None,
+        };
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![
+                entry("/root/a", EntryKind::Directory),
+                entry("/root/b", EntryKind::File),
+                entry("/root/c", EntryKind::Directory),
+                entry("/root/d", EntryKind::File),
+                entry("/root/e", EntryKind::Fifo),
+            ],
+        );
+        fs.set_dir_entries(
+            "/root/a",
+            vec![
+                entry("/root/a/x", EntryKind::File),
+                entry("/root/a/y", EntryKind::Directory),
+                entry("/root/a/z", EntryKind::File),
+                entry("/root/a/zz", EntryKind::Socket),
+            ],
+        );
+        fs.set_dir_entries("/root/a/y", vec![entry("/root/a/y/deep", EntryKind::File)]);
+        fs.set_error("/root/c", "Permission denied");
+
+        let walk_options = WalkOptions::default();
+        let tree = walk_dir(&fs, Path::new("/root"), &walk_options)
+            .await
+            .unwrap();
+
+        for max_children in [None, Some(0), Some(1), Some(2), Some(10)] {
+            let options = RenderOptions { max_children };
+
+            let mut expected = Vec::new();
+            write_children_with_options(&mut expected, &tree.children, &options).unwrap();
+
+            let mut walker = walk_dir_streaming(&fs, Path::new("/root"), &walk_options)
+                .await
+                .unwrap();
+            let mut actual = Vec::new();
+            write_walk(&mut actual, &mut walker, &options)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                String::from_utf8(actual).unwrap(),
+                String::from_utf8(expected).unwrap(),
+                "max_children = {max_children:?}"
+            );
+        }
+    }
 }
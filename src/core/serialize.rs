@@ -0,0 +1,429 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::core::diff::{TreeDiff, diff_trees};
+use crate::core::transform::sort_for_display;
+use crate::core::walk::WalkOptions;
+use crate::models::{DirTree, EntryKind};
+use crate::{Error, Result};
+
+/// Version of the JSON schema written by [`TreeDocument`]. Bump it whenever
+/// the serialized shape of `DirTree` changes incompatibly.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A serialized walk result: the tree plus the root's display name and the
+/// options it was walked with, tagged with the schema version it was written
+/// with.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TreeDocument {
+    pub version: u32,
+    /// Display name of the root, as passed to `write_root`
+    pub root: String,
+    /// Walk options that decided which entries the tree contains and their order
+    #[serde(default, skip_serializing_if = "WalkFilters::is_empty")]
+    pub filters: WalkFilters,
+    pub tree: DirTree,
+}
+
+/// The options of a walk that shape its tree, as recorded in a
+/// [`TreeDocument`] so the walk can be repeated.
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WalkFilters {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dirs_only: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dirs_first: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newer_than: Option<SystemTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub older_than: Option<SystemTime>,
+    /// Selected entry kinds, in `find -type` letter order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_types: Option<Vec<EntryKind>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub prune: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_limit: Option<usize>,
+}
+
+impl WalkFilters {
+    /// Returns true if the walk used the default options.
+    pub fn is_empty(&self) -> bool {
+        *self == WalkFilters::default()
+    }
+}
+
+impl From<&WalkOptions> for WalkFilters {
+    fn from(options: &WalkOptions) -> Self {
+        let entry_types = options.entry_types.as_ref().map(|kinds| {
+            let mut kinds: Vec<EntryKind> = kinds.iter().copied().collect();
+            kinds.sort_by_key(|kind| kind.type_char());
+            kinds
+        });
+        WalkFilters {
+            max_depth: options.max_depth,
+            ignore_pattern: options.ignore_pattern.clone(),
+            dirs_only: options.dirs_only,
+            dirs_first: options.dirs_first,
+            min_size: options.min_size,
+            max_size: options.max_size,
+            newer_than: options.newer_than,
+            older_than: options.older_than,
+            entry_types,
+            prune: options.prune,
+            file_limit: options.file_limit,
+        }
+    }
+}
+
+impl From<&WalkFilters> for WalkOptions {
+    fn from(filters: &WalkFilters) -> Self {
+        WalkOptions {
+            max_depth: filters.max_depth,
+            ignore_pattern: filters.ignore_pattern.clone(),
+            dirs_only: filters.dirs_only,
+            dirs_first: filters.dirs_first,
+            entry_types: filters
+                .entry_types
+                .as_ref()
+                .map(|kinds| kinds.iter().copied().collect()),
+            prune: filters.prune,
+            min_size: filters.min_size,
+            max_size: filters.max_size,
+            newer_than: filters.newer_than,
+            older_than: filters.older_than,
+            file_limit: filters.file_limit,
+            ..WalkOptions::default()
+        }
+    }
+}
+
+impl TreeDocument {
+    pub fn new(root: impl Into<String>, tree: DirTree) -> Self {
+        TreeDocument {
+            version: SCHEMA_VERSION,
+            root: root.into(),
+            filters: WalkFilters::default(),
+            tree,
+        }
+    }
+
+    /// Record the options of the walk that produced the tree.
+    pub fn with_filters(mut self, options: &WalkOptions) -> Self {
+        self.filters = WalkFilters::from(options);
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("tree documents always serialize")
+    }
+
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(writer, self).map_err(invalid_tree)
+    }
+
+    /// Load a document, rejecting schema versions this build does not know.
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str::<Self>(json)
+            .map_err(invalid_tree)?
+            .check_version()
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        serde_json::from_reader::<_, Self>(reader)
+            .map_err(invalid_tree)?
+            .check_version()
+    }
+
+    /// Load a document from a file, e.g. a `--snapshot`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|err| Error::io(path, &err))?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// Save the document to a file as JSON.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_json() + "\n").map_err(|err| Error::io(path, &err))
+    }
+
+    /// Diff a tree against the saved one. The tree must be walked with the
+    /// recorded options, e.g. `WalkOptions::from(&document.filters)`, except
+    /// that it may be sorted differently: the saved tree is re-sorted to
+    /// `dirs_first` first.
+    ///
+    /// Documents keep no metadata, so only additions and removals are found,
+    /// plus files whose digest changed when both sides were hashed with the
+    /// same algorithm.
+    pub fn diff(&self, tree: &DirTree, dirs_first: bool) -> TreeDiff {
+        let mut saved = self.tree.clone();
+        sort_for_display(&mut saved, dirs_first);
+        diff_trees(&saved, tree, dirs_first, |path, old| {
+            let new = tree.get(path).and_then(|node| node.digest.as_ref());
+            matches!(
+                (&old.digest, new),
+                (Some(old), Some(new)) if old.algorithm == new.algorithm && old.hex != new.hex
+            )
+        })
+    }
+
+    fn check_version(self) -> Result<Self> {
+        if self.version == SCHEMA_VERSION {
+            Ok(self)
+        } else {
+            Err(Error::InvalidTree {
+                message: format!(
+                    "unsupported schema version {} (expected {SCHEMA_VERSION})",
+                    self.version
+                ),
+            })
+        }
+    }
+}
+
+fn invalid_tree(err: serde_json::Error) -> Error {
+    Error::InvalidTree {
+        message: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::diff::{Change, DiffSummary};
+    use crate::core::render::{write_children, write_root};
+    use crate::models::{Digest, GitStatus, HashAlgorithm, TreeNode};
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    fn sample_tree() -> DirTree {
+        let node = |name: &str, kind: EntryKind, children: Vec<TreeNode>| TreeNode {
+            name: name.to_owned(),
+            kind,
+            error: None,
+            exceeded_filelimit: None,
+            status: None,
+            digest: None,
+            duplicate: None,
+            children,
+        };
+        DirTree {
+            error: None,
+            exceeded_filelimit: None,
+            children: vec![
+                TreeNode {
+                    error: Some(Error::Io {
+                        path: "/root/locked".into(),
+                        kind: std::io::ErrorKind::PermissionDenied,
+                        message: "Permission denied".to_owned(),
+                    }),
+                    ..node("locked/", EntryKind::Directory, vec![])
+                },
+                TreeNode {
+                    status: Some(GitStatus::Modified),
+                    ..node(
+                        "src/",
+                        EntryKind::Directory,
+                        vec![node("main.rs", EntryKind::File, vec![])],
+                    )
+                },
+                node("link", EntryKind::Symlink, vec![]),
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let document = TreeDocument::new("project", sample_tree());
+        let loaded = TreeDocument::from_json(&document.to_json()).unwrap();
+        assert_eq!(loaded, document);
+    }
+
+    #[test]
+    fn schema_uses_stable_names() {
+        let json = TreeDocument::new("project", sample_tree()).to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["version"], SCHEMA_VERSION);
+        let locked = &value["tree"]["children"][0];
+        assert_eq!(locked["kind"], "directory");
+        assert_eq!(locked["error"]["type"], "io");
+        assert_eq!(locked["error"]["kind"], "permission_denied");
+        // Empty and absent fields are omitted
+        assert!(locked.get("children").is_none());
+        assert!(locked.get("exceeded_filelimit").is_none());
+        assert!(locked.get("status").is_none());
+        assert_eq!(value["tree"]["children"][1]["status"], "modified");
+    }
+
+    #[test]
+    fn records_walk_filters() {
+        let options = WalkOptions {
+            max_depth: Some(3),
+            ignore_pattern: Some("target|*.log".to_owned()),
+            dirs_first: true,
+            file_limit: Some(100),
+            min_size: Some(1024),
+            max_size: Some(1 << 20),
+            newer_than: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(86_400)),
+            older_than: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(172_800)),
+            entry_types: Some(HashSet::from([EntryKind::Symlink, EntryKind::File])),
+            prune: true,
+            ..WalkOptions::default()
+        };
+        let document = TreeDocument::new("project", sample_tree()).with_filters(&options);
+
+        let json = document.to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let filters = &value["filters"];
+        assert_eq!(filters["min_size"], 1024);
+        assert_eq!(filters["newer_than"]["secs_since_epoch"], 86_400);
+        assert_eq!(
+            filters["entry_types"],
+            serde_json::json!(["file", "symlink"])
+        );
+        assert_eq!(filters["prune"], true);
+        assert_eq!(filters["ignore_pattern"], "target|*.log");
+        assert_eq!(filters["dirs_first"], true);
+
+        let loaded = TreeDocument::from_json(&json).unwrap();
+        assert_eq!(loaded, document);
+        assert_eq!(loaded.filters, WalkFilters::from(&options));
+
+        // The recorded options repeat the walk
+        let repeated = WalkOptions::from(&loaded.filters);
+        assert_eq!(WalkFilters::from(&repeated), loaded.filters);
+        assert_eq!(repeated.entry_types, options.entry_types);
+        assert!(repeated.show_hidden);
+    }
+
+    #[test]
+    fn omits_filters_of_an_unfiltered_walk() {
+        let document =
+            TreeDocument::new("project", sample_tree()).with_filters(&WalkOptions::default());
+        let value: serde_json::Value = serde_json::from_str(&document.to_json()).unwrap();
+        assert!(value.get("filters").is_none());
+        assert!(
+            TreeDocument::from_json(&document.to_json())
+                .unwrap()
+                .filters
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let json = r#"{"version": 99, "root": ".", "tree": {}}"#;
+        let err = TreeDocument::from_json(json).unwrap_err();
+        assert!(matches!(err, Error::InvalidTree { .. }));
+        assert!(err.to_string().contains("unsupported schema version 99"));
+    }
+
+    #[test]
+    fn loaded_tree_renders_like_the_original() {
+        let tree = sample_tree();
+        let json = TreeDocument::new("project", tree.clone()).to_json();
+        let loaded = TreeDocument::from_reader(json.as_bytes()).unwrap();
+
+        let render = |root: &str, tree: &DirTree| {
+            let mut out = Vec::new();
+            write_root(&mut out, root, tree.error.as_ref(), tree.exceeded_filelimit).unwrap();
+            write_children(&mut out, &tree.children).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(render(&loaded.root, &loaded.tree), render("project", &tree));
+    }
+
+    #[test]
+    fn saves_and_loads_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("snapshot.json");
+        let document = TreeDocument::new("project", sample_tree());
+        document.save(&path).unwrap();
+        assert_eq!(TreeDocument::load(&path).unwrap(), document);
+
+        let missing = temp.path().join("missing.json");
+        let err = TreeDocument::load(&missing).unwrap_err();
+        assert_eq!(err.io_kind(), Some(std::io::ErrorKind::NotFound));
+        assert_eq!(err.path(), Some(missing.as_path()));
+    }
+
+    #[test]
+    fn diffs_a_tree_against_the_document() {
+        let mut tree = sample_tree();
+        tree.children.retain(|node| node.name != "link");
+        let lib = TreeNode {
+            name: "lib.rs".to_owned(),
+            ..tree.children[1].children[0].clone()
+        };
+        tree.children[1].children.push(lib);
+        sort_for_display(&mut tree, false);
+        let document = TreeDocument::new("project", sample_tree());
+
+        let diff = document.diff(&tree, false);
+        assert_eq!(
+            diff.summary,
+            DiffSummary {
+                added: 1,
+                removed: 1,
+                modified: 0,
+            }
+        );
+        assert_eq!(diff.children[0].name, "link");
+        assert_eq!(diff.children[0].change, Change::Removed);
+
+        let mut unchanged = sample_tree();
+        sort_for_display(&mut unchanged, false);
+        assert!(document.diff(&unchanged, false).summary.is_empty());
+    }
+
+    #[test]
+    fn diff_compares_digests_of_the_same_algorithm() {
+        let hashed = |algorithm, hex: &str| {
+            let mut tree = sample_tree();
+            tree.children[1].children[0].digest = Some(Digest {
+                algorithm,
+                hex: hex.to_owned(),
+            });
+            sort_for_display(&mut tree, false);
+            tree
+        };
+        let document = TreeDocument::new("project", hashed(HashAlgorithm::Sha256, "aa"));
+        let modified = |tree: &DirTree| document.diff(tree, false).summary.modified;
+
+        assert_eq!(modified(&hashed(HashAlgorithm::Sha256, "aa")), 0);
+        assert_eq!(modified(&hashed(HashAlgorithm::Sha256, "bb")), 1);
+        // Digests of different algorithms, or a missing one, cannot be compared
+        assert_eq!(modified(&hashed(HashAlgorithm::Blake3, "bb")), 0);
+        let mut unhashed = sample_tree();
+        sort_for_display(&mut unhashed, false);
+        assert_eq!(modified(&unhashed), 0);
+    }
+
+    #[test]
+    fn diff_ignores_a_different_sort_order() {
+        let mut tree = sample_tree();
+        tree.children.push(TreeNode {
+            name: "a-file".to_owned(),
+            ..tree.children[2].clone()
+        });
+        sort_for_display(&mut tree, false);
+        assert_eq!(tree.children[0].name, "a-file");
+        let document = TreeDocument::new("project", tree.clone());
+
+        let mut dirs_first = tree;
+        sort_for_display(&mut dirs_first, true);
+        let diff = document.diff(&dirs_first, true);
+        assert!(diff.summary.is_empty());
+        assert_eq!(diff.children[0].name, "locked/");
+    }
+}
@@ -0,0 +1,451 @@
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+use crate::Result;
+use crate::core::walk::{
+    CompiledPatterns, WalkOptions, compare_for_display, compile_patterns, is_listed, is_prunable,
+    should_recurse,
+};
+use crate::models::{DirTree, EntryKind, GitStatus, TreeNode};
+
+/// Collapse chains of directories that each contain only a single directory
+/// into one node, e.g. `src/` -> `main/` -> `java/` becomes `src/main/java/`.
+///
+/// A directory with an error or a file-limit annotation ends a chain, so the
+/// annotation stays attached to the merged node.
+pub fn compact_dirs(tree: &mut DirTree) {
+    compact_children(&mut tree.children);
+}
+
+fn compact_children(children: &mut [TreeNode]) {
+    for node in children.iter_mut() {
+        while is_compactable(node) {
+            let child = node.children.pop().expect("single child");
+            node.name.push_str(&child.name);
+            node.error = child.error;
+            node.exceeded_filelimit = child.exceeded_filelimit;
+            node.status = child.status;
+            node.digest = child.digest;
+            node.duplicate = child.duplicate;
+            node.children = child.children;
+        }
+        compact_children(&mut node.children);
+    }
+}
+
+fn is_compactable(node: &TreeNode) -> bool {
+    node.kind == EntryKind::Directory
+        && node.error.is_none()
+        && node.exceeded_filelimit.is_none()
+        && node.children.len() == 1
+        && node.children[0].kind == EntryKind::Directory
+}
+
+/// Keep only the nodes for which `keep` returns true, given each node's path
+/// relative to the root. Rejecting a directory drops its whole subtree.
+pub fn retain<F: FnMut(&Path, &TreeNode) -> bool>(tree: &mut DirTree, mut keep: F) {
+    retain_children(&mut tree.children, &mut PathBuf::new(), &mut keep);
+}
+
+fn retain_children<F: FnMut(&Path, &TreeNode) -> bool>(
+    children: &mut Vec<TreeNode>,
+    path: &mut PathBuf,
+    keep: &mut F,
+) {
+    children.retain_mut(|node| {
+        path.push(node.name.trim_end_matches('/'));
+        let kept = keep(path, node);
+        if kept {
+            retain_children(&mut node.children, path, keep);
+        }
+        path.pop();
+        kept
+    });
+}
+
+/// Set each node's `status` from `status_of`, given the node's path relative
+/// to the root.
+pub fn annotate_status<F: FnMut(&Path, &TreeNode) -> Option<GitStatus>>(
+    tree: &mut DirTree,
+    mut status_of: F,
+) {
+    annotate_children(&mut tree.children, &mut PathBuf::new(), &mut status_of);
+}
+
+fn annotate_children<F: FnMut(&Path, &TreeNode) -> Option<GitStatus>>(
+    children: &mut [TreeNode],
+    path: &mut PathBuf,
+    status_of: &mut F,
+) {
+    for node in children {
+        path.push(node.name.trim_end_matches('/'));
+        node.status = status_of(path, node);
+        annotate_children(&mut node.children, path, status_of);
+        path.pop();
+    }
+}
+
+/// Drop directories with nothing to show, bottom-up, as `--prune` does.
+/// Directories with an error or file-limit annotation are kept.
+pub fn prune_empty_dirs(tree: &mut DirTree) {
+    prune_children(&mut tree.children);
+}
+
+fn prune_children(children: &mut Vec<TreeNode>) {
+    children.retain_mut(|node| {
+        prune_children(&mut node.children);
+        !is_prunable(node)
+    });
+}
+
+/// Re-sort every directory's children with `compare`.
+pub fn sort_by<F: FnMut(&TreeNode, &TreeNode) -> Ordering>(tree: &mut DirTree, mut compare: F) {
+    sort_children(&mut tree.children, &mut compare);
+}
+
+fn sort_children<F: FnMut(&TreeNode, &TreeNode) -> Ordering>(
+    children: &mut [TreeNode],
+    compare: &mut F,
+) {
+    children.sort_by(|a, b| compare(a, b));
+    for node in children {
+        sort_children(&mut node.children, compare);
+    }
+}
+
+/// Re-sort into the walk's display order: alphabetical by rendered name,
+/// optionally with directories first.
+pub fn sort_for_display(tree: &mut DirTree, dirs_first: bool) {
+    sort_by(tree, |a, b| {
+        compare_for_display(&a.name, a.kind, &b.name, b.kind, dirs_first)
+    });
+}
+
+/// Limit the tree to `max_depth` levels, with the same meaning as `-L`.
+/// Directories at the last level are kept but left unopened.
+pub fn truncate_depth(tree: &mut DirTree, max_depth: usize) {
+    let options = WalkOptions {
+        max_depth: Some(max_depth),
+        ..WalkOptions::default()
+    };
+    truncate_children(&mut tree.children, &options, 0);
+}
+
+fn truncate_children(children: &mut [TreeNode], options: &WalkOptions, depth: usize) {
+    let recurse = should_recurse(options, depth);
+    for node in children
+        .iter_mut()
+        .filter(|node| node.kind == EntryKind::Directory)
+    {
+        if recurse {
+            truncate_children(&mut node.children, options, depth + 1);
+        } else {
+            close_dir(node);
+        }
+    }
+}
+
+/// Reapply walk options to an already-built tree, producing what a fresh walk
+/// with `options` would show, as long as the tree was walked with options
+/// that were no narrower.
+///
+/// Size and modification-time filters need entry metadata, which a `DirTree`
+/// does not keep, so they are not applied.
+/// Returns an error if the ignore pattern is invalid.
+pub fn apply_walk_options(tree: &mut DirTree, options: &WalkOptions) -> Result<()> {
+    let compiled_patterns = compile_patterns(options)?;
+    if tree.error.is_none() && tree.exceeded_filelimit.is_none() {
+        tree.exceeded_filelimit =
+            apply_to_listing(&mut tree.children, options, &compiled_patterns, 0);
+    }
+    Ok(())
+}
+
+/// Filter, limit, recurse, prune and sort one directory's children, in the
+/// order the walk does. Returns the entry count if it exceeds the file limit.
+fn apply_to_listing(
+    children: &mut Vec<TreeNode>,
+    options: &WalkOptions,
+    compiled_patterns: &Option<CompiledPatterns>,
+    depth: usize,
+) -> Option<usize> {
+    children.retain(|node| {
+        is_listed(
+            node.name.trim_end_matches('/'),
+            node.kind,
+            options,
+            compiled_patterns,
+        )
+    });
+
+    if let Some(limit) = options.file_limit
+        && children.len() > limit
+    {
+        let count = children.len();
+        children.clear();
+        return Some(count);
+    }
+
+    let recurse = should_recurse(options, depth);
+    children.retain_mut(|node| {
+        if node.kind != EntryKind::Directory {
+            return true;
+        }
+        if !recurse {
+            close_dir(node);
+            return true;
+        }
+        if node.error.is_none() && node.exceeded_filelimit.is_none() {
+            node.exceeded_filelimit =
+                apply_to_listing(&mut node.children, options, compiled_patterns, depth + 1);
+        }
+        !(options.prune && is_prunable(node))
+    });
+
+    children
+        .sort_by(|a, b| compare_for_display(&a.name, a.kind, &b.name, b.kind, options.dirs_first));
+    None
+}
+
+/// Turn a directory into one the walk never opened.
+fn close_dir(node: &mut TreeNode) {
+    node.error = None;
+    node.exceeded_filelimit = None;
+    node.children.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    fn dir(name: &str, children: Vec<TreeNode>) -> TreeNode {
+        TreeNode {
+            name: name.to_owned(),
+            kind: EntryKind::Directory,
+            error: None,
+            exceeded_filelimit: None,
+            status: None,
+            digest: None,
+            duplicate: None,
+            children,
+        }
+    }
+
+    fn file(name: &str) -> TreeNode {
+        TreeNode {
+            name: name.to_owned(),
+            kind: EntryKind::File,
+            error: None,
+            exceeded_filelimit: None,
+            status: None,
+            digest: None,
+            duplicate: None,
+            children: vec![],
+        }
+    }
+
+    #[test]
+    fn compacts_single_directory_chains() {
+        let mut tree = DirTree {
+            error: None,
+            exceeded_filelimit: None,
+            children: vec![
+                dir(
+                    "src/",
+                    vec![dir(
+                        "main/",
+                        vec![dir("java/", vec![file("App.java"), file("Util.java")])],
+                    )],
+                ),
+                file("pom.xml"),
+            ],
+        };
+
+        compact_dirs(&mut tree);
+
+        assert_eq!(tree.children[0].name, "src/main/java/");
+        assert_eq!(tree.children[0].children.len(), 2);
+        assert_eq!(tree.children[1].name, "pom.xml");
+    }
+
+    #[test]
+    fn stops_at_directories_with_files_or_errors() {
+        let mut errored = dir("secret/", vec![]);
+        errored.error = Some(Error::Io {
+            path: "a/secret".into(),
+            kind: std::io::ErrorKind::PermissionDenied,
+            message: "Permission denied".to_owned(),
+        });
+        let mut tree = DirTree {
+            error: None,
+            exceeded_filelimit: None,
+            children: vec![
+                dir(
+                    "a/",
+                    vec![dir(
+                        "b/",
+                        vec![file("x"), dir("c/", vec![dir("d/", vec![])])],
+                    )],
+                ),
+                dir("only-file/", vec![file("y")]),
+                dir("outer/", vec![errored]),
+            ],
+        };
+
+        compact_dirs(&mut tree);
+
+        assert_eq!(tree.children[0].name, "a/b/");
+        assert_eq!(tree.children[0].children[1].name, "c/d/");
+        assert_eq!(tree.children[1].name, "only-file/");
+        assert_eq!(tree.children[2].name, "outer/secret/");
+        assert_eq!(
+            tree.children[2].error.as_ref().and_then(Error::io_kind),
+            Some(std::io::ErrorKind::PermissionDenied)
+        );
+    }
+
+    fn names(children: &[TreeNode]) -> Vec<&str> {
+        children.iter().map(|node| node.name.as_str()).collect()
+    }
+
+    fn sample() -> DirTree {
+        DirTree {
+            error: None,
+            exceeded_filelimit: None,
+            children: vec![
+                dir("b/", vec![dir("empty/", vec![]), file("x.rs")]),
+                file("a.txt"),
+                dir("c/", vec![file("y.txt")]),
+            ],
+        }
+    }
+
+    #[test]
+    fn retain_sees_relative_paths_and_drops_subtrees() {
+        let mut tree = sample();
+        let mut seen = Vec::new();
+        retain(&mut tree, |path, _| {
+            seen.push(path.display().to_string());
+            path != Path::new("c")
+        });
+
+        assert_eq!(seen, ["b", "b/empty", "b/x.rs", "a.txt", "c"]);
+        assert_eq!(names(&tree.children), ["b/", "a.txt"]);
+    }
+
+    #[test]
+    fn prune_removes_directories_left_empty() {
+        let mut tree = sample();
+        retain(&mut tree, |_, node| {
+            node.kind == EntryKind::Directory || node.name.ends_with(".rs")
+        });
+        prune_empty_dirs(&mut tree);
+
+        assert_eq!(names(&tree.children), ["b/"]);
+        assert_eq!(names(&tree.children[0].children), ["x.rs"]);
+    }
+
+    #[test]
+    fn sorts_recursively() {
+        let mut tree = sample();
+        sort_by(&mut tree, |a, b| b.name.cmp(&a.name));
+        assert_eq!(names(&tree.children), ["c/", "b/", "a.txt"]);
+        assert_eq!(names(&tree.children[1].children), ["x.rs", "empty/"]);
+
+        sort_for_display(&mut tree, true);
+        assert_eq!(names(&tree.children), ["b/", "c/", "a.txt"]);
+        assert_eq!(names(&tree.children[0].children), ["empty/", "x.rs"]);
+    }
+
+    #[test]
+    fn truncate_depth_matches_level_flag() {
+        let mut tree = sample();
+        truncate_depth(&mut tree, 1);
+        assert_eq!(names(&tree.children), ["b/", "a.txt", "c/"]);
+        assert!(tree.children.iter().all(|node| node.children.is_empty()));
+    }
+
+    #[test]
+    fn apply_walk_options_matches_a_fresh_walk() {
+        use crate::core::walk::walk_dir_blocking;
+        use crate::fs::MockFileSystem;
+        use crate::models::FsEntry;
+        use std::collections::HashSet;
+
+        let entry = |path: &str, kind: EntryKind| FsEntry {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap().to_owned(),
+            kind,
+            metadata: None,
+        };
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![
+                entry("/root/src", EntryKind::Directory),
+                entry("/root/.git", EntryKind::Directory),
+                entry("/root/README.md", EntryKind::File),
+                entry("/root/docs", EntryKind::Directory),
+                entry("/root/run", EntryKind::Symlink),
+            ],
+        );
+        fs.set_dir_entries(
+            "/root/src",
+            vec![
+                entry("/root/src/main.rs", EntryKind::File),
+                entry("/root/src/util", EntryKind::Directory),
+                entry("/root/src/lib.rs", EntryKind::File),
+            ],
+        );
+        fs.set_dir_entries(
+            "/root/src/util",
+            vec![entry("/root/src/util/mod.rs", EntryKind::File)],
+        );
+        fs.set_dir_entries(
+            "/root/.git",
+            vec![entry("/root/.git/HEAD", EntryKind::File)],
+        );
+        fs.set_dir_entries(
+            "/root/docs",
+            vec![entry("/root/docs/guide.md", EntryKind::File)],
+        );
+
+        let root = Path::new("/root");
+        let full = walk_dir_blocking(&fs, root, &WalkOptions::default()).unwrap();
+
+        let cases = [
+            WalkOptions {
+                max_depth: Some(2),
+                dirs_first: true,
+                ..WalkOptions::default()
+            },
+            WalkOptions {
+                ignore_pattern: Some("*.md|util".to_owned()),
+                show_hidden: false,
+                ..WalkOptions::default()
+            },
+            WalkOptions {
+                entry_types: Some(HashSet::from([EntryKind::Symlink])),
+                prune: true,
+                ..WalkOptions::default()
+            },
+            WalkOptions {
+                dirs_only: true,
+                ..WalkOptions::default()
+            },
+            WalkOptions {
+                ignore_pattern: Some("README.md|run|.git".to_owned()),
+                file_limit: Some(2),
+                ..WalkOptions::default()
+            },
+        ];
+
+        for options in cases {
+            let mut tree = full.clone();
+            apply_walk_options(&mut tree, &options).unwrap();
+            let expected = walk_dir_blocking(&fs, root, &options).unwrap();
+            assert_eq!(tree, expected, "{options:?}");
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+#[cfg(feature = "async")]
+use crate::core::walk::{WalkEntry, WalkEvent, WalkOptions, walk_dir_streaming};
+#[cfg(feature = "async")]
+use crate::fs::FileSystem;
+use crate::models::{DirTree, EntryKind, TreeNode};
+
+/// An entry handed to a [`TreeVisitor`].
+#[derive(Clone, Copy, Debug)]
+pub struct VisitEntry<'a> {
+    /// Path relative to the root of the walk
+    pub path: &'a Path,
+    /// Rendered name (directories carry a trailing `/`)
+    pub name: &'a str,
+    pub kind: EntryKind,
+    /// Nesting level, where children of the root are at depth 0
+    pub depth: usize,
+    pub error: Option<&'a Error>,
+    /// Entry count of a directory that was not opened because it exceeds the file limit
+    pub exceeded_filelimit: Option<usize>,
+}
+
+/// Callbacks for processing a tree in display order.
+///
+/// Every `visit_dir` is matched by a `leave_dir`, with the directory's
+/// children (if any) visited in between. All methods default to doing nothing.
+pub trait TreeVisitor {
+    fn visit_dir(&mut self, _dir: &VisitEntry<'_>) {}
+
+    fn leave_dir(&mut self, _dir: &VisitEntry<'_>) {}
+
+    fn visit_file(&mut self, _file: &VisitEntry<'_>) {}
+
+    fn visit_symlink(&mut self, _link: &VisitEntry<'_>) {}
+
+    /// FIFOs, sockets, devices and unclassified entries.
+    fn visit_special(&mut self, _entry: &VisitEntry<'_>) {}
+
+    /// A directory could not be read. `path` is relative to the root, so the
+    /// root itself is the empty path.
+    fn visit_error(&mut self, _path: &Path, _error: &Error) {}
+}
+
+/// Walk a directory, calling the visitor for each entry in display order.
+///
+/// Entries are streamed from the filesystem, so no `DirTree` is built.
+/// Returns an error if the ignore pattern is invalid.
+#[cfg(feature = "async")]
+pub async fn walk_with_visitor<F: FileSystem, V: TreeVisitor>(
+    fs: &F,
+    dir: &Path,
+    options: &WalkOptions,
+    visitor: &mut V,
+) -> crate::Result<()> {
+    let mut walker = walk_dir_streaming(fs, dir, options).await?;
+    let relative = |path: &Path| path.strip_prefix(dir).unwrap_or(path).to_path_buf();
+    // Directories entered but not yet left, to hand back to `leave_dir`
+    let mut open_dirs: Vec<(PathBuf, WalkEntry)> = Vec::new();
+
+    if let Some(error) = walker.root_error() {
+        visitor.visit_error(Path::new(""), error);
+    }
+
+    while let Some(event) = walker.next().await {
+        match event {
+            WalkEvent::Entry(entry) => {
+                let path = relative(&entry.path);
+                let visit_entry = walk_visit_entry(&path, &entry);
+                dispatch(visitor, &visit_entry);
+                if let Some(error) = entry.error.as_ref() {
+                    visitor.visit_error(&path, error);
+                }
+                if entry.kind == EntryKind::Directory {
+                    visitor.leave_dir(&visit_entry);
+                }
+            }
+            WalkEvent::EnterDir(entry) => {
+                let path = relative(&entry.path);
+                visitor.visit_dir(&walk_visit_entry(&path, &entry));
+                open_dirs.push((path, entry));
+            }
+            WalkEvent::LeaveDir => {
+                if let Some((path, entry)) = open_dirs.pop() {
+                    visitor.leave_dir(&walk_visit_entry(&path, &entry));
+                }
+            }
+            // Reported above from the entry that failed, or from the root
+            WalkEvent::Error(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+impl DirTree {
+    /// Visit an already-built tree in display order, with paths relative to
+    /// its root.
+    pub fn accept<V: TreeVisitor>(&self, visitor: &mut V) {
+        let mut path = PathBuf::new();
+        if let Some(error) = self.error.as_ref() {
+            visitor.visit_error(&path, error);
+        }
+        accept_children(&self.children, visitor, &mut path, 0);
+    }
+}
+
+fn accept_children<V: TreeVisitor>(
+    children: &[TreeNode],
+    visitor: &mut V,
+    path: &mut PathBuf,
+    depth: usize,
+) {
+    for node in children {
+        path.push(node.name.trim_end_matches('/'));
+
+        dispatch(visitor, &node_visit_entry(path, node, depth));
+        if let Some(error) = node.error.as_ref() {
+            visitor.visit_error(path, error);
+        }
+        if node.kind == EntryKind::Directory {
+            accept_children(&node.children, visitor, path, depth + 1);
+            visitor.leave_dir(&node_visit_entry(path, node, depth));
+        }
+
+        path.pop();
+    }
+}
+
+fn node_visit_entry<'a>(path: &'a Path, node: &'a TreeNode, depth: usize) -> VisitEntry<'a> {
+    VisitEntry {
+        path,
+        name: &node.name,
+        kind: node.kind,
+        depth,
+        error: node.error.as_ref(),
+        exceeded_filelimit: node.exceeded_filelimit,
+    }
+}
+
+#[cfg(feature = "async")]
+fn walk_visit_entry<'a>(path: &'a Path, entry: &'a WalkEntry) -> VisitEntry<'a> {
+    VisitEntry {
+        path,
+        name: &entry.name,
+        kind: entry.kind,
+        depth: entry.depth,
+        error: entry.error.as_ref(),
+        exceeded_filelimit: entry.exceeded_filelimit,
+    }
+}
+
+/// Call the kind-specific visitor method for an entry.
+fn dispatch<V: TreeVisitor>(visitor: &mut V, entry: &VisitEntry<'_>) {
+    match entry.kind {
+        EntryKind::Directory => visitor.visit_dir(entry),
+        EntryKind::File => visitor.visit_file(entry),
+        EntryKind::Symlink => visitor.visit_symlink(entry),
+        EntryKind::Fifo
+        | EntryKind::Socket
+        | EntryKind::BlockDevice
+        | EntryKind::CharDevice
+        | EntryKind::Other => visitor.visit_special(entry),
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use super::*;
+    use crate::core::walk::walk_dir;
+    use crate::fs::MockFileSystem;
+    use crate::models::FsEntry;
+
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<String>,
+    }
+
+    impl TreeVisitor for Recorder {
+        fn visit_dir(&mut self, dir: &VisitEntry<'_>) {
+            self.calls
+                .push(format!("dir {} {}", dir.path.display(), dir.depth));
+        }
+
+        fn leave_dir(&mut self, dir: &VisitEntry<'_>) {
+            self.calls.push(format!("leave {}", dir.path.display()));
+        }
+
+        fn visit_file(&mut self, file: &VisitEntry<'_>) {
+            self.calls.push(format!("file {}", file.path.display()));
+        }
+
+        fn visit_symlink(&mut self, link: &VisitEntry<'_>) {
+            self.calls.push(format!("symlink {}", link.path.display()));
+        }
+
+        fn visit_special(&mut self, entry: &VisitEntry<'_>) {
+            self.calls.push(format!("special {}", entry.path.display()));
+        }
+
+        fn visit_error(&mut self, path: &Path, error: &Error) {
+            self.calls.push(format!("error {} {error}", path.display()));
+        }
+    }
+
+    fn fixture() -> MockFileSystem {
+        let entry = |path: &str, kind: EntryKind| FsEntry {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap().to_owned(),
+            kind,
+            metadata: None,
+        };
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![
+                entry("/root/src", EntryKind::Directory),
+                entry("/root/link", EntryKind::Symlink),
+                entry("/root/pipe", EntryKind::Fifo),
+                entry("/root/secret", EntryKind::Directory),
+            ],
+        );
+        fs.set_dir_entries(
+            "/root/src",
+            vec![entry("/root/src/lib.rs", EntryKind::File)],
+        );
+        fs.set_error("/root/secret", "Permission denied");
+        fs
+    }
+
+    const EXPECTED: [&str; 8] = [
+        "symlink link",
+        "special pipe",
+        "dir secret 0",
+        "error secret Permission denied",
+        "leave secret",
+        "dir src 0",
+        "file src/lib.rs",
+        "leave src",
+    ];
+
+    #[tokio::test]
+    async fn walk_with_visitor_reports_entries_in_display_order() {
+        let fs = fixture();
+        let mut recorder = Recorder::default();
+        walk_with_visitor(
+            &fs,
+            Path::new("/root"),
+            &WalkOptions::default(),
+            &mut recorder,
+        )
+        .await
+        .unwrap();
+        assert_eq!(recorder.calls, EXPECTED);
+    }
+
+    #[tokio::test]
+    async fn accept_matches_walk_with_visitor() {
+        let fs = fixture();
+        let tree = walk_dir(&fs, Path::new("/root"), &WalkOptions::default())
+            .await
+            .unwrap();
+        let mut recorder = Recorder::default();
+        tree.accept(&mut recorder);
+        assert_eq!(recorder.calls, EXPECTED);
+    }
+
+    #[tokio::test]
+    async fn root_error_is_reported_with_empty_path() {
+        let fs = MockFileSystem::default();
+        fs.set_error("/root", "Permission denied");
+
+        let mut recorder = Recorder::default();
+        walk_with_visitor(
+            &fs,
+            Path::new("/root"),
+            &WalkOptions::default(),
+            &mut recorder,
+        )
+        .await
+        .unwrap();
+        assert_eq!(recorder.calls, vec!["error  Permission denied"]);
+    }
+}
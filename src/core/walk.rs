@@ -1,11 +1,19 @@
+use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::path::Path;
+#[cfg(feature = "async")]
+use std::path::PathBuf;
+use std::time::SystemTime;
 
-use anyhow::Context;
+#[cfg(feature = "async")]
+use futures_util::Stream;
 use regex::RegexSet;
 
+use crate::fs::BlockingFileSystem;
+#[cfg(feature = "async")]
 use crate::fs::FileSystem;
-use crate::models::{DirTree, EntryKind, TreeNode};
+use crate::models::{DirTree, EntryKind, FsEntry, TreeNode};
+use crate::{Error, Result};
 
 /// Pre-compiled ignore patterns for efficient matching.
 /// Separates exact-match patterns from glob patterns for optimal performance.
@@ -19,7 +27,7 @@ pub struct CompiledPatterns {
 impl CompiledPatterns {
     /// Compile a pipe-separated pattern string into efficient matchers.
     /// Returns an error if any glob pattern produces invalid regex.
-    pub fn new(pattern: &str) -> anyhow::Result<Self> {
+    pub fn new(pattern: &str) -> Result<Self> {
         let mut exact_matches = HashSet::new();
         let mut regex_patterns = Vec::new();
 
@@ -43,8 +51,10 @@ impl CompiledPatterns {
             None
         } else {
             Some(
-                RegexSet::new(&regex_patterns)
-                    .with_context(|| format!("Invalid ignore pattern: {pattern}"))?,
+                RegexSet::new(&regex_patterns).map_err(|err| Error::InvalidPattern {
+                    pattern: pattern.to_owned(),
+                    message: err.to_string(),
+                })?,
             )
         };
 
@@ -107,6 +117,39 @@ pub struct WalkOptions {
     pub dirs_only: bool,
     /// Whether to sort directories before files
     pub dirs_first: bool,
+    /// Entry kinds to show (None means all). Directories are always traversed,
+    /// and shown as containers, even when not selected.
+    pub entry_types: Option<HashSet<EntryKind>>,
+    /// Whether to drop directories left empty after filtering
+    pub prune: bool,
+    /// Minimum size in bytes for non-directory entries (inclusive)
+    pub min_size: Option<u64>,
+    /// Maximum size in bytes for non-directory entries (inclusive)
+    pub max_size: Option<u64>,
+    /// Only show non-directory entries modified after this time
+    pub newer_than: Option<SystemTime>,
+    /// Only show non-directory entries modified before this time
+    pub older_than: Option<SystemTime>,
+    /// Do not descend into directories with more than this many entries
+    pub file_limit: Option<usize>,
+}
+
+impl WalkOptions {
+    /// Whether any size or modification-time filter is set, so the walk
+    /// needs entry metadata from the filesystem.
+    pub fn has_metadata_filters(&self) -> bool {
+        self.min_size.is_some()
+            || self.max_size.is_some()
+            || self.newer_than.is_some()
+            || self.older_than.is_some()
+    }
+
+    /// Whether directories left empty after filtering are dropped.
+    /// Metadata filters imply pruning: directories are only kept for their
+    /// matching descendants, which under `dirs_only` are not shown.
+    fn prunes_empty_dirs(&self) -> bool {
+        self.prune || self.has_metadata_filters()
+    }
 }
 
 impl Default for WalkOptions {
@@ -117,6 +160,13 @@ impl Default for WalkOptions {
             show_hidden: true, // Current behavior: show hidden files by default
             dirs_only: false,
             dirs_first: false,
+            entry_types: None,
+            prune: false,
+            min_size: None,
+            max_size: None,
+            newer_than: None,
+            older_than: None,
+            file_limit: None,
         }
     }
 }
@@ -125,130 +175,817 @@ impl Default for WalkOptions {
 ///
 /// This is the public entry point that starts traversal at depth 0.
 /// Returns an error if the ignore pattern is invalid.
-pub async fn walk_dir<F: FileSystem>(
-    fs: &F,
-    dir: &Path,
-    options: &WalkOptions,
-) -> anyhow::Result<DirTree> {
+#[cfg(feature = "async")]
+pub async fn walk_dir<F: FileSystem>(fs: &F, dir: &Path, options: &WalkOptions) -> Result<DirTree> {
     // Pre-compile patterns once before traversal
-    let compiled_patterns = match &options.ignore_pattern {
-        Some(pattern) => Some(CompiledPatterns::new(pattern)?),
-        None => None,
-    };
+    let compiled_patterns = compile_patterns(options)?;
 
-    Ok(walk_dir_internal(fs, dir, options, &compiled_patterns, 0).await)
+    Ok(walk_dir_internal(fs, dir, options, &compiled_patterns, 0)
+        .await
+        .tree)
 }
 
 /// Internal recursive function that tracks current depth.
+#[cfg(feature = "async")]
 async fn walk_dir_internal<F: FileSystem>(
     fs: &F,
     dir: &Path,
     options: &WalkOptions,
     compiled_patterns: &Option<CompiledPatterns>,
     current_depth: usize,
-) -> DirTree {
-    let entries = match fs.read_dir(dir).await {
+) -> WalkedDir {
+    let listing = list_dir(fs, dir, options, compiled_patterns).await;
+    if listing.is_unopened() {
+        return listing.into_unopened();
+    }
+
+    let mut hidden_matches = listing.hidden_matches;
+    let mut children = Vec::with_capacity(listing.entries.len());
+    for (rendered, entry) in listing.entries {
+        let subtree =
+            if entry.kind == EntryKind::Directory && should_recurse(options, current_depth) {
+                Some(
+                    Box::pin(walk_dir_internal(
+                        fs,
+                        &entry.path,
+                        options,
+                        compiled_patterns,
+                        current_depth + 1,
+                    ))
+                    .await,
+                )
+            } else {
+                None
+            };
+        hidden_matches |= push_node(&mut children, rendered, entry.kind, subtree, options);
+    }
+
+    WalkedDir {
+        tree: DirTree {
+            error: None,
+            exceeded_filelimit: None,
+            children,
+        },
+        hidden_matches,
+    }
+}
+
+/// Walk a directory tree with the given options, without an async runtime.
+///
+/// Produces the same `DirTree` as [`walk_dir`].
+/// Returns an error if the ignore pattern is invalid.
+pub fn walk_dir_blocking<F: BlockingFileSystem>(
+    fs: &F,
+    dir: &Path,
+    options: &WalkOptions,
+) -> Result<DirTree> {
+    let compiled_patterns = compile_patterns(options)?;
+
+    Ok(walk_dir_blocking_internal(fs, dir, options, &compiled_patterns, 0).tree)
+}
+
+/// Blocking counterpart of `walk_dir_internal`.
+fn walk_dir_blocking_internal<F: BlockingFileSystem>(
+    fs: &F,
+    dir: &Path,
+    options: &WalkOptions,
+    compiled_patterns: &Option<CompiledPatterns>,
+    current_depth: usize,
+) -> WalkedDir {
+    let listing = prepare_listing(fs.read_dir(dir), options, compiled_patterns);
+    if listing.is_unopened() {
+        return listing.into_unopened();
+    }
+
+    let mut hidden_matches = listing.hidden_matches;
+    let mut children = Vec::with_capacity(listing.entries.len());
+    for (rendered, entry) in listing.entries {
+        let subtree =
+            if entry.kind == EntryKind::Directory && should_recurse(options, current_depth) {
+                Some(walk_dir_blocking_internal(
+                    fs,
+                    &entry.path,
+                    options,
+                    compiled_patterns,
+                    current_depth + 1,
+                ))
+            } else {
+                None
+            };
+        hidden_matches |= push_node(&mut children, rendered, entry.kind, subtree, options);
+    }
+
+    WalkedDir {
+        tree: DirTree {
+            error: None,
+            exceeded_filelimit: None,
+            children,
+        },
+        hidden_matches,
+    }
+}
+
+pub(crate) fn compile_patterns(options: &WalkOptions) -> Result<Option<CompiledPatterns>> {
+    match &options.ignore_pattern {
+        Some(pattern) => Ok(Some(CompiledPatterns::new(pattern)?)),
+        None => Ok(None),
+    }
+}
+
+/// A walked directory, and whether it holds files hidden by `dirs_only`
+/// that pass the size and time filters, directly or in kept subdirectories.
+struct WalkedDir {
+    tree: DirTree,
+    hidden_matches: bool,
+}
+
+/// Append a listed entry to `children`, attaching the walked subtree of a
+/// descended directory. Returns whether the subtree holds hidden matches.
+fn push_node(
+    children: &mut Vec<TreeNode>,
+    name: String,
+    kind: EntryKind,
+    subtree: Option<WalkedDir>,
+    options: &WalkOptions,
+) -> bool {
+    let mut node = TreeNode {
+        name,
+        kind,
+        error: None,
+        exceeded_filelimit: None,
+        status: None,
+        digest: None,
+        duplicate: None,
+        children: Vec::new(),
+    };
+
+    let mut hidden_matches = false;
+    if let Some(WalkedDir {
+        tree,
+        hidden_matches: hidden,
+    }) = subtree
+    {
+        node.error = tree.error;
+        node.exceeded_filelimit = tree.exceeded_filelimit;
+        node.children = tree.children;
+        hidden_matches = hidden;
+
+        // Drop directories with nothing left to show after filtering, unless
+        // they only look empty because `dirs_only` hides their matching files
+        if options.prunes_empty_dirs() && is_prunable(&node) && !hidden_matches {
+            return false;
+        }
+    }
+
+    children.push(node);
+    hidden_matches
+}
+
+/// Whether a walked directory has nothing to show. Directories carrying an
+/// error or file-limit annotation are never prunable.
+pub(crate) fn is_prunable(node: &TreeNode) -> bool {
+    node.kind == EntryKind::Directory
+        && node.error.is_none()
+        && node.exceeded_filelimit.is_none()
+        && node.children.is_empty()
+}
+
+/// The filtered and sorted contents of a single directory.
+struct DirListing {
+    error: Option<Error>,
+    exceeded_filelimit: Option<usize>,
+    /// Entries paired with their rendered names, in display order
+    entries: Vec<(String, FsEntry)>,
+    /// Whether `dirs_only` hid a file that passes the size and time filters
+    hidden_matches: bool,
+}
+
+impl DirListing {
+    /// Whether the directory could not be read or exceeds the file limit.
+    fn is_unopened(&self) -> bool {
+        self.error.is_some() || self.exceeded_filelimit.is_some()
+    }
+
+    fn into_unopened(self) -> WalkedDir {
+        WalkedDir {
+            tree: DirTree {
+                error: self.error,
+                exceeded_filelimit: self.exceeded_filelimit,
+                children: Vec::new(),
+            },
+            hidden_matches: false,
+        }
+    }
+}
+
+/// Read a single directory, then filter and sort its entries for display.
+#[cfg(feature = "async")]
+async fn list_dir<F: FileSystem>(
+    fs: &F,
+    dir: &Path,
+    options: &WalkOptions,
+    compiled_patterns: &Option<CompiledPatterns>,
+) -> DirListing {
+    prepare_listing(fs.read_dir(dir).await, options, compiled_patterns)
+}
+
+/// Filter and sort the result of reading a single directory for display.
+fn prepare_listing(
+    entries: Result<Vec<FsEntry>>,
+    options: &WalkOptions,
+    compiled_patterns: &Option<CompiledPatterns>,
+) -> DirListing {
+    let entries = match entries {
         Ok(entries) => entries,
         Err(err) => {
-            return DirTree {
-                error: Some(err.to_string()),
-                children: Vec::new(),
+            return DirListing {
+                error: Some(err),
+                exceeded_filelimit: None,
+                entries: Vec::new(),
+                hidden_matches: false,
             };
         }
     };
 
+    // With `-d`, directories are kept for matching files they do not show
+    let hidden_matches = options.dirs_only
+        && options.has_metadata_filters()
+        && entries.iter().any(|entry| {
+            entry.kind != EntryKind::Directory
+                && is_selected(&entry.name, entry.kind, options, compiled_patterns)
+                && matches_metadata_filters(entry, options)
+        });
+
     // Filter entries based on options
     let filtered_entries: Vec<_> = entries
         .into_iter()
         .filter(|entry| {
-            // Filter hidden files if show_hidden is false
-            if !options.show_hidden && entry.name.starts_with('.') {
-                return false;
-            }
-
-            // Filter by compiled ignore patterns
-            if let Some(patterns) = compiled_patterns
-                && patterns.matches(&entry.name)
-            {
-                return false;
-            }
-
-            // Filter non-directories if dirs_only is true
-            if options.dirs_only && entry.kind != EntryKind::Directory {
-                return false;
-            }
-
-            true
+            is_listed(&entry.name, entry.kind, options, compiled_patterns)
+                // Filter non-directories by size and modification time
+                && (entry.kind == EntryKind::Directory || matches_metadata_filters(entry, options))
         })
         .collect();
 
+    // Leave oversized directories unopened, recording how many entries they hold
+    if let Some(limit) = options.file_limit
+        && filtered_entries.len() > limit
+    {
+        return DirListing {
+            error: None,
+            exceeded_filelimit: Some(filtered_entries.len()),
+            entries: Vec::new(),
+            hidden_matches: false,
+        };
+    }
+
     let mut entries_with_rendered: Vec<(String, _)> = filtered_entries
         .into_iter()
         .map(|entry| (rendered_name(&entry.name, entry.kind), entry))
         .collect();
 
-    // Sort entries: dirs-first if enabled, then alphabetically by rendered name
-    if options.dirs_first {
-        entries_with_rendered.sort_by(|(name_a, entry_a), (name_b, entry_b)| {
-            match (entry_a.kind, entry_b.kind) {
-                (EntryKind::Directory, EntryKind::Directory) => name_a.cmp(name_b),
-                (EntryKind::Directory, _) => std::cmp::Ordering::Less,
-                (_, EntryKind::Directory) => std::cmp::Ordering::Greater,
-                _ => name_a.cmp(name_b),
-            }
-        });
+    entries_with_rendered.sort_by(|(name_a, entry_a), (name_b, entry_b)| {
+        compare_for_display(
+            name_a,
+            entry_a.kind,
+            name_b,
+            entry_b.kind,
+            options.dirs_first,
+        )
+    });
+
+    DirListing {
+        error: None,
+        exceeded_filelimit: None,
+        entries: entries_with_rendered,
+        hidden_matches,
+    }
+}
+
+/// Whether an entry passes the name and kind filters: hidden files, ignore
+/// patterns, `dirs_only` and entry types. Shared with the post-walk transforms.
+pub(crate) fn is_listed(
+    name: &str,
+    kind: EntryKind,
+    options: &WalkOptions,
+    compiled_patterns: &Option<CompiledPatterns>,
+) -> bool {
+    // Filter non-directories if dirs_only is true
+    if options.dirs_only && kind != EntryKind::Directory {
+        return false;
+    }
+
+    is_selected(name, kind, options, compiled_patterns)
+}
+
+/// Whether an entry passes the name and kind filters other than `dirs_only`.
+fn is_selected(
+    name: &str,
+    kind: EntryKind,
+    options: &WalkOptions,
+    compiled_patterns: &Option<CompiledPatterns>,
+) -> bool {
+    // Filter hidden files if show_hidden is false
+    if !options.show_hidden && name.starts_with('.') {
+        return false;
+    }
+
+    // Filter by compiled ignore patterns
+    if let Some(patterns) = compiled_patterns
+        && patterns.matches(name)
+    {
+        return false;
+    }
+
+    // Filter non-directories by kind; directories are kept so that
+    // matching descendants remain reachable
+    if let Some(types) = &options.entry_types
+        && kind != EntryKind::Directory
+        && !types.contains(&kind)
+    {
+        return false;
+    }
+
+    true
+}
+
+/// Display order of two siblings: dirs-first if enabled, then alphabetically
+/// by rendered name.
+pub(crate) fn compare_for_display(
+    name_a: &str,
+    kind_a: EntryKind,
+    name_b: &str,
+    kind_b: EntryKind,
+    dirs_first: bool,
+) -> Ordering {
+    if dirs_first {
+        match (kind_a, kind_b) {
+            (EntryKind::Directory, EntryKind::Directory) => name_a.cmp(name_b),
+            (EntryKind::Directory, _) => Ordering::Less,
+            (_, EntryKind::Directory) => Ordering::Greater,
+            _ => name_a.cmp(name_b),
+        }
     } else {
-        entries_with_rendered.sort_by(|(a, _), (b, _)| a.cmp(b));
+        name_a.cmp(name_b)
+    }
+}
+
+/// Only recurse into directories if we haven't reached max depth.
+/// Note: -L 1 means "show 1 level of children", so at depth 0 we should not recurse
+pub(crate) fn should_recurse(options: &WalkOptions, current_depth: usize) -> bool {
+    match options.max_depth {
+        Some(max) => current_depth + 1 < max,
+        None => true,
+    }
+}
+
+/// An entry produced by a streaming walk.
+#[cfg(feature = "async")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WalkEntry {
+    /// Full path of the entry
+    pub path: PathBuf,
+    /// Rendered name (directories carry a trailing `/`)
+    pub name: String,
+    pub kind: EntryKind,
+    /// Nesting level, where children of the root are at depth 0
+    pub depth: usize,
+    /// Position among the displayed siblings
+    pub index: usize,
+    /// Number of displayed siblings, including this entry
+    pub sibling_count: usize,
+    /// Whether this is the last displayed sibling
+    pub is_last: bool,
+    pub error: Option<Error>,
+    /// Entry count of a directory that was not opened because it exceeds the file limit
+    pub exceeded_filelimit: Option<usize>,
+}
+
+/// An event produced by [`DirWalker`], in final display order.
+#[cfg(feature = "async")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WalkEvent {
+    /// An entry that is not descended: a file, symlink or special file, or a
+    /// directory that is depth-limited, unreadable or over the file limit.
+    Entry(WalkEntry),
+    /// A directory whose children follow, terminated by a matching `LeaveDir`.
+    EnterDir(WalkEntry),
+    /// All children of the most recent `EnterDir` have been produced.
+    LeaveDir,
+    /// A directory could not be read. Produced right after the `Entry` for
+    /// that directory (which also carries the error), or first for the root.
+    Error(Error),
+}
+
+/// One open directory in a streaming walk.
+#[cfg(feature = "async")]
+struct Level {
+    entries: std::vec::IntoIter<ListedEntry>,
+    len: usize,
+    depth: usize,
+}
+
+/// A directory listing for streaming whose subdirectories are checked for
+/// pruning lazily. Each directory is read once: a subdirectory kept by the
+/// check carries what was read while checking it, for the walk to resume.
+#[cfg(feature = "async")]
+struct StreamListing {
+    error: Option<Error>,
+    exceeded_filelimit: Option<usize>,
+    entries: Vec<ListedEntry>,
+    /// Number of leading entries whose pruning is decided
+    checked: usize,
+    /// Whether `dirs_only` hid a file that passes the size and time filters
+    hidden_matches: bool,
+}
+
+#[cfg(feature = "async")]
+struct ListedEntry {
+    rendered: String,
+    entry: FsEntry,
+    /// Contents of a directory, read while checking it for pruning
+    contents: Option<Box<StreamListing>>,
+}
+
+#[cfg(feature = "async")]
+impl StreamListing {
+    async fn read<F: FileSystem>(
+        fs: &F,
+        dir: &Path,
+        options: &WalkOptions,
+        compiled_patterns: &Option<CompiledPatterns>,
+    ) -> Self {
+        let listing = list_dir(fs, dir, options, compiled_patterns).await;
+        StreamListing {
+            error: listing.error,
+            exceeded_filelimit: listing.exceeded_filelimit,
+            entries: listing
+                .entries
+                .into_iter()
+                .map(|(rendered, entry)| ListedEntry {
+                    rendered,
+                    entry,
+                    contents: None,
+                })
+                .collect(),
+            checked: 0,
+            hidden_matches: listing.hidden_matches,
+        }
+    }
+
+    fn is_unopened(&self) -> bool {
+        self.error.is_some() || self.exceeded_filelimit.is_some()
+    }
+}
+
+/// Incremental directory walker that yields entries in final display order.
+///
+/// Only the sorted listing of each directory on the current path is held in
+/// memory, so usage grows with depth times directory width rather than with
+/// the size of the whole tree. When pruning is in effect, each subdirectory
+/// is read ahead until its first visible entry before it is yielded, and
+/// what was read is kept until the walk reaches it.
+#[cfg(feature = "async")]
+pub struct DirWalker<'a, F: FileSystem> {
+    fs: &'a F,
+    options: &'a WalkOptions,
+    compiled_patterns: Option<CompiledPatterns>,
+    root_error: Option<Error>,
+    root_exceeded_filelimit: Option<usize>,
+    levels: Vec<Level>,
+    /// Event to produce before resuming the walk
+    pending: Option<WalkEvent>,
+}
+
+/// Start a streaming walk of a directory with the given options.
+///
+/// The root directory is read immediately so that its error state is
+/// available before any entries are produced.
+/// Returns an error if the ignore pattern is invalid.
+#[cfg(feature = "async")]
+pub async fn walk_dir_streaming<'a, F: FileSystem>(
+    fs: &'a F,
+    dir: &Path,
+    options: &'a WalkOptions,
+) -> Result<DirWalker<'a, F>> {
+    let compiled_patterns = compile_patterns(options)?;
+
+    let mut listing = StreamListing::read(fs, dir, options, &compiled_patterns).await;
+    settle_pruning(fs, &mut listing, options, &compiled_patterns, 0, false).await;
+    let pending = listing.error.clone().map(WalkEvent::Error);
+    let mut walker = DirWalker {
+        fs,
+        options,
+        compiled_patterns,
+        root_error: listing.error,
+        root_exceeded_filelimit: listing.exceeded_filelimit,
+        levels: Vec::new(),
+        pending,
+    };
+    walker.push_level(listing.entries, 0);
+    Ok(walker)
+}
+
+/// Walk a directory as an async `Stream` of events in deterministic display
+/// order, honoring all `WalkOptions`.
+///
+/// This is a convenience over [`walk_dir_streaming`] and
+/// [`DirWalker::into_stream`]. Returns an error if the ignore pattern is invalid.
+#[cfg(feature = "async")]
+pub async fn walk_dir_stream<'a, F: FileSystem>(
+    fs: &'a F,
+    dir: &Path,
+    options: &'a WalkOptions,
+) -> Result<impl Stream<Item = WalkEvent> + Send + 'a> {
+    Ok(walk_dir_streaming(fs, dir, options).await?.into_stream())
+}
+
+#[cfg(feature = "async")]
+impl<'a, F: FileSystem> DirWalker<'a, F> {
+    /// Error from reading the root directory, if any.
+    pub fn root_error(&self) -> Option<&Error> {
+        self.root_error.as_ref()
+    }
+
+    /// Entry count of the root directory if it exceeds the file limit.
+    pub fn root_exceeded_filelimit(&self) -> Option<usize> {
+        self.root_exceeded_filelimit
     }
 
-    let mut children = Vec::with_capacity(entries_with_rendered.len());
-    for (rendered, entry) in entries_with_rendered {
-        let mut node = TreeNode {
-            name: rendered,
+    /// Produce the next event, or None once the walk is complete.
+    pub async fn next(&mut self) -> Option<WalkEvent> {
+        if let Some(event) = self.pending.take() {
+            return Some(event);
+        }
+
+        let level = self.levels.last_mut()?;
+        let index = level.len - level.entries.len();
+        let Some(ListedEntry {
+            rendered: name,
+            entry,
+            contents,
+        }) = level.entries.next()
+        else {
+            self.levels.pop();
+            return (!self.levels.is_empty()).then_some(WalkEvent::LeaveDir);
+        };
+
+        let depth = level.depth;
+        let mut walk_entry = WalkEntry {
+            path: entry.path,
+            name,
             kind: entry.kind,
+            depth,
+            index,
+            sibling_count: level.len,
+            is_last: level.entries.len() == 0,
             error: None,
-            children: Vec::new(),
+            exceeded_filelimit: None,
         };
 
-        // Only recurse into directories if we haven't reached max depth
-        // Note: -L 1 means "show 1 level of children", so at depth 0 we should not recurse
-        if entry.kind == EntryKind::Directory {
-            let should_recurse = match options.max_depth {
-                Some(max) => current_depth + 1 < max,
-                None => true,
+        if entry.kind == EntryKind::Directory && should_recurse(self.options, depth) {
+            let mut listing = match contents {
+                Some(contents) => *contents,
+                None => {
+                    StreamListing::read(
+                        self.fs,
+                        &walk_entry.path,
+                        self.options,
+                        &self.compiled_patterns,
+                    )
+                    .await
+                }
             };
+            settle_pruning(
+                self.fs,
+                &mut listing,
+                self.options,
+                &self.compiled_patterns,
+                depth + 1,
+                false,
+            )
+            .await;
+            if listing.is_unopened() {
+                self.pending = listing.error.clone().map(WalkEvent::Error);
+                walk_entry.error = listing.error;
+                walk_entry.exceeded_filelimit = listing.exceeded_filelimit;
+            } else {
+                self.push_level(listing.entries, depth + 1);
+                return Some(WalkEvent::EnterDir(walk_entry));
+            }
+        }
+
+        Some(WalkEvent::Entry(walk_entry))
+    }
+
+    /// Convert the walker into an async `Stream` of its events.
+    pub fn into_stream(self) -> impl Stream<Item = WalkEvent> + Send + 'a {
+        futures_util::stream::unfold(self, |mut walker| async move {
+            let event = walker.next().await?;
+            Some((event, walker))
+        })
+    }
+
+    /// Drop the not-yet-produced siblings of the most recent entry, returning
+    /// how many were skipped. Skipped directories are never read.
+    pub fn skip_remaining_siblings(&mut self) -> usize {
+        match self.levels.last_mut() {
+            Some(level) => {
+                let skipped = level.entries.len();
+                level.entries = Vec::new().into_iter();
+                skipped
+            }
+            None => 0,
+        }
+    }
+
+    fn push_level(&mut self, entries: Vec<ListedEntry>, depth: usize) {
+        self.levels.push(Level {
+            len: entries.len(),
+            entries: entries.into_iter(),
+            depth,
+        });
+    }
+}
 
-            if should_recurse {
-                let subtree = Box::pin(walk_dir_internal(
+/// Decide which subdirectories of a listing at `current_depth` survive
+/// pruning, dropping the others so that sibling positions are final. With
+/// `first_only`, stop at the first kept entry and return whether there is
+/// one. Directories are read at most once across repeated calls.
+#[cfg(feature = "async")]
+async fn settle_pruning<F: FileSystem>(
+    fs: &F,
+    listing: &mut StreamListing,
+    options: &WalkOptions,
+    compiled_patterns: &Option<CompiledPatterns>,
+    current_depth: usize,
+    first_only: bool,
+) -> bool {
+    if !options.prunes_empty_dirs() {
+        listing.checked = listing.entries.len();
+        return !listing.entries.is_empty();
+    }
+
+    // Entries already checked are all kept, and hidden matches keep the
+    // directory without showing anything
+    let mut any_kept = listing.checked > 0 || listing.hidden_matches;
+    while listing.checked < listing.entries.len() {
+        if first_only && any_kept {
+            return true;
+        }
+
+        let listed = &mut listing.entries[listing.checked];
+        let kept = if listed.entry.kind != EntryKind::Directory
+            || !should_recurse(options, current_depth)
+        {
+            true
+        } else {
+            let mut contents = match listed.contents.take() {
+                Some(contents) => contents,
+                None => Box::new(
+                    StreamListing::read(fs, &listed.entry.path, options, compiled_patterns).await,
+                ),
+            };
+            let kept = contents.is_unopened()
+                || Box::pin(settle_pruning(
                     fs,
-                    &entry.path,
+                    &mut contents,
                     options,
                     compiled_patterns,
                     current_depth + 1,
+                    true,
                 ))
                 .await;
-                node.error = subtree.error;
-                node.children = subtree.children;
-            }
+            listed.contents = Some(contents);
+            kept
+        };
+
+        if kept {
+            listing.checked += 1;
+            any_kept = true;
+        } else {
+            listing.entries.remove(listing.checked);
         }
+    }
+    any_kept
+}
 
-        children.push(node);
+/// Check an entry against the size and modification-time filters.
+/// Entries without metadata never match an active filter.
+fn matches_metadata_filters(entry: &FsEntry, options: &WalkOptions) -> bool {
+    if !options.has_metadata_filters() {
+        return true;
     }
 
-    DirTree {
-        error: None,
-        children,
+    let Some(metadata) = entry.metadata.as_ref() else {
+        return false;
+    };
+
+    if options.min_size.is_some_and(|min| metadata.size < min)
+        || options.max_size.is_some_and(|max| metadata.size > max)
+    {
+        return false;
+    }
+
+    if options.newer_than.is_some() || options.older_than.is_some() {
+        let Some(modified) = metadata.modified else {
+            return false;
+        };
+        if options.newer_than.is_some_and(|newer| modified <= newer)
+            || options.older_than.is_some_and(|older| modified >= older)
+        {
+            return false;
+        }
     }
+
+    true
 }
 
 fn rendered_name(name: &str, kind: EntryKind) -> String {
     match kind {
         EntryKind::Directory => format!("{name}/"),
-        EntryKind::File | EntryKind::Symlink | EntryKind::Other => name.to_owned(),
+        EntryKind::File
+        | EntryKind::Symlink
+        | EntryKind::Fifo
+        | EntryKind::Socket
+        | EntryKind::BlockDevice
+        | EntryKind::CharDevice
+        | EntryKind::Other => name.to_owned(),
     }
 }
 
 #[cfg(test)]
+mod blocking_tests {
+    use super::*;
+    use crate::fs::MockFileSystem;
+    use std::path::PathBuf;
+
+    fn entry(path: &str, kind: EntryKind) -> FsEntry {
+        FsEntry {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap().to_owned(),
+            kind,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn blocking_walk_builds_sorted_tree_with_errors() {
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![
+                entry("/root/src", EntryKind::Directory),
+                entry("/root/Cargo.toml", EntryKind::File),
+                entry("/root/secret", EntryKind::Directory),
+            ],
+        );
+        fs.set_dir_entries(
+            "/root/src",
+            vec![entry("/root/src/lib.rs", EntryKind::File)],
+        );
+        fs.set_error("/root/secret", "Permission denied");
+
+        let tree = walk_dir_blocking(&fs, Path::new("/root"), &WalkOptions::default()).unwrap();
+        let names: Vec<&str> = tree.children.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["Cargo.toml", "secret/", "src/"]);
+        assert_eq!(
+            tree.children[1].error.as_ref().map(ToString::to_string),
+            Some("Permission denied".to_owned())
+        );
+        assert_eq!(tree.children[2].children[0].name, "lib.rs");
+    }
+
+    #[test]
+    fn blocking_walk_honors_depth_and_pruning() {
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![
+                entry("/root/empty", EntryKind::Directory),
+                entry("/root/full", EntryKind::Directory),
+            ],
+        );
+        fs.set_dir_entries("/root/empty", vec![]);
+        fs.set_dir_entries("/root/full", vec![entry("/root/full/a", EntryKind::File)]);
+
+        let options = WalkOptions {
+            prune: true,
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir_blocking(&fs, Path::new("/root"), &options).unwrap();
+        let names: Vec<&str> = tree.children.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["full/"]);
+
+        let options = WalkOptions {
+            max_depth: Some(1),
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir_blocking(&fs, Path::new("/root"), &options).unwrap();
+        assert!(tree.children.iter().all(|n| n.children.is_empty()));
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
 mod tests {
     use super::*;
     use crate::fs::MockFileSystem;
@@ -265,16 +1002,19 @@ mod tests {
                     path: PathBuf::from("/root/a-dir"),
                     name: "a".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/a-file"),
                     name: "a".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/b"),
                     name: "b".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
             ],
         );
@@ -295,6 +1035,7 @@ mod tests {
                 path: PathBuf::from("/root/secret"),
                 name: "secret".to_owned(),
                 kind: EntryKind::Directory,
+                metadata: None,
             }],
         );
         fs.set_error("/root/secret", "Permission denied");
@@ -307,8 +1048,9 @@ mod tests {
         assert!(
             tree.children[0]
                 .error
-                .as_deref()
-                .unwrap_or("")
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default()
                 .contains("Permission")
         );
         assert!(tree.children[0].children.is_empty());
@@ -323,6 +1065,7 @@ mod tests {
                 path: PathBuf::from("/root/link"),
                 name: "link".to_owned(),
                 kind: EntryKind::Symlink,
+                metadata: None,
             }],
         );
         fs.set_dir_entries(
@@ -331,6 +1074,7 @@ mod tests {
                 path: PathBuf::from("/root/link/child"),
                 name: "child".to_owned(),
                 kind: EntryKind::File,
+                metadata: None,
             }],
         );
 
@@ -359,6 +1103,7 @@ mod tests {
                 path: PathBuf::from("/root/level1"),
                 name: "level1".to_owned(),
                 kind: EntryKind::Directory,
+                metadata: None,
             }],
         );
         fs.set_dir_entries(
@@ -367,6 +1112,7 @@ mod tests {
                 path: PathBuf::from("/root/level1/level2"),
                 name: "level2".to_owned(),
                 kind: EntryKind::Directory,
+                metadata: None,
             }],
         );
         fs.set_dir_entries(
@@ -375,6 +1121,7 @@ mod tests {
                 path: PathBuf::from("/root/level1/level2/level3"),
                 name: "level3".to_owned(),
                 kind: EntryKind::Directory,
+                metadata: None,
             }],
         );
 
@@ -408,6 +1155,7 @@ mod tests {
                 path: PathBuf::from("/root/a"),
                 name: "a".to_owned(),
                 kind: EntryKind::Directory,
+                metadata: None,
             }],
         );
         fs.set_dir_entries(
@@ -416,6 +1164,7 @@ mod tests {
                 path: PathBuf::from("/root/a/b"),
                 name: "b".to_owned(),
                 kind: EntryKind::Directory,
+                metadata: None,
             }],
         );
         fs.set_dir_entries(
@@ -424,6 +1173,7 @@ mod tests {
                 path: PathBuf::from("/root/a/b/c"),
                 name: "c".to_owned(),
                 kind: EntryKind::File,
+                metadata: None,
             }],
         );
 
@@ -445,11 +1195,13 @@ mod tests {
                     path: PathBuf::from("/root/keep"),
                     name: "keep".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/node_modules"),
                     name: "node_modules".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
             ],
         );
@@ -473,21 +1225,25 @@ mod tests {
                     path: PathBuf::from("/root/keep"),
                     name: "keep".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/node_modules"),
                     name: "node_modules".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/dist"),
                     name: "dist".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/.git"),
                     name: ".git".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
             ],
         );
@@ -513,11 +1269,13 @@ mod tests {
                     path: PathBuf::from("/root/dir"),
                     name: "dir".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/file.txt"),
                     name: "file.txt".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
             ],
         );
@@ -542,11 +1300,13 @@ mod tests {
                     path: PathBuf::from("/root/dir"),
                     name: "dir".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/link"),
                     name: "link".to_owned(),
                     kind: EntryKind::Symlink,
+                    metadata: None,
                 },
             ],
         );
@@ -561,6 +1321,574 @@ mod tests {
         assert_eq!(tree.children[0].name, "dir/");
     }
 
+    #[tokio::test]
+    async fn dirs_only_excludes_special_files() {
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![
+                FsEntry {
+                    path: PathBuf::from("/root/dir"),
+                    name: "dir".to_owned(),
+                    kind: EntryKind::Directory,
+                    metadata: None,
+                },
+                FsEntry {
+                    path: PathBuf::from("/root/pipe"),
+                    name: "pipe".to_owned(),
+                    kind: EntryKind::Fifo,
+                    metadata: None,
+                },
+                FsEntry {
+                    path: PathBuf::from("/root/sock"),
+                    name: "sock".to_owned(),
+                    kind: EntryKind::Socket,
+                    metadata: None,
+                },
+                FsEntry {
+                    path: PathBuf::from("/root/null"),
+                    name: "null".to_owned(),
+                    kind: EntryKind::CharDevice,
+                    metadata: None,
+                },
+            ],
+        );
+        fs.set_dir_entries("/root/dir", vec![]);
+
+        let options = WalkOptions {
+            dirs_only: true,
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir(&fs, Path::new("/root"), &options).await.unwrap();
+        assert_eq!(tree.children.len(), 1);
+        assert_eq!(tree.children[0].name, "dir/");
+    }
+
+    // --- Type filter tests ---
+
+    fn type_filter_fixture() -> MockFileSystem {
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![
+                FsEntry {
+                    path: PathBuf::from("/root/empty"),
+                    name: "empty".to_owned(),
+                    kind: EntryKind::Directory,
+                    metadata: None,
+                },
+                FsEntry {
+                    path: PathBuf::from("/root/file"),
+                    name: "file".to_owned(),
+                    kind: EntryKind::File,
+                    metadata: None,
+                },
+                FsEntry {
+                    path: PathBuf::from("/root/sub"),
+                    name: "sub".to_owned(),
+                    kind: EntryKind::Directory,
+                    metadata: None,
+                },
+            ],
+        );
+        fs.set_dir_entries("/root/empty", vec![]);
+        fs.set_dir_entries(
+            "/root/sub",
+            vec![
+                FsEntry {
+                    path: PathBuf::from("/root/sub/link"),
+                    name: "link".to_owned(),
+                    kind: EntryKind::Symlink,
+                    metadata: None,
+                },
+                FsEntry {
+                    path: PathBuf::from("/root/sub/other"),
+                    name: "other".to_owned(),
+                    kind: EntryKind::File,
+                    metadata: None,
+                },
+            ],
+        );
+        fs
+    }
+
+    #[tokio::test]
+    async fn type_filter_keeps_directories_as_containers() {
+        let fs = type_filter_fixture();
+
+        let options = WalkOptions {
+            entry_types: Some(HashSet::from([EntryKind::Symlink])),
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir(&fs, Path::new("/root"), &options).await.unwrap();
+        let names: Vec<&str> = tree.children.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["empty/", "sub/"]);
+        assert_eq!(tree.children[1].children.len(), 1);
+        assert_eq!(tree.children[1].children[0].name, "link");
+    }
+
+    #[tokio::test]
+    async fn type_filter_with_prune_drops_empty_directories() {
+        let fs = type_filter_fixture();
+
+        let options = WalkOptions {
+            entry_types: Some(HashSet::from([EntryKind::Symlink])),
+            prune: true,
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir(&fs, Path::new("/root"), &options).await.unwrap();
+        let names: Vec<&str> = tree.children.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["sub/"]);
+    }
+
+    // --- Size and modification time filter tests ---
+
+    fn file_with_metadata(path: &str, size: u64, modified_secs: u64) -> FsEntry {
+        let path = PathBuf::from(path);
+        FsEntry {
+            name: path.file_name().unwrap().to_string_lossy().into_owned(),
+            path,
+            kind: EntryKind::File,
+            metadata: Some(crate::models::EntryMetadata {
+                size,
+                modified: Some(
+                    SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(modified_secs),
+                ),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn metadata_filter_fixture() -> MockFileSystem {
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![
+                file_with_metadata("/root/big.bin", 4096, 200),
+                file_with_metadata("/root/small.txt", 10, 50),
+                FsEntry {
+                    path: PathBuf::from("/root/nested"),
+                    name: "nested".to_owned(),
+                    kind: EntryKind::Directory,
+                    metadata: None,
+                },
+                FsEntry {
+                    path: PathBuf::from("/root/quiet"),
+                    name: "quiet".to_owned(),
+                    kind: EntryKind::Directory,
+                    metadata: None,
+                },
+            ],
+        );
+        fs.set_dir_entries(
+            "/root/nested",
+            vec![file_with_metadata("/root/nested/huge.iso", 1 << 20, 100)],
+        );
+        fs.set_dir_entries(
+            "/root/quiet",
+            vec![file_with_metadata("/root/quiet/tiny", 1, 300)],
+        );
+        fs
+    }
+
+    #[tokio::test]
+    async fn size_filter_keeps_only_directories_with_matches() {
+        let fs = metadata_filter_fixture();
+
+        let options = WalkOptions {
+            min_size: Some(1024),
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir(&fs, Path::new("/root"), &options).await.unwrap();
+        let names: Vec<&str> = tree.children.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["big.bin", "nested/"]);
+        assert_eq!(tree.children[1].children[0].name, "huge.iso");
+
+        let options = WalkOptions {
+            max_size: Some(10),
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir(&fs, Path::new("/root"), &options).await.unwrap();
+        let names: Vec<&str> = tree.children.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["quiet/", "small.txt"]);
+    }
+
+    #[tokio::test]
+    async fn dirs_only_keeps_directories_holding_matching_files() {
+        let fs = metadata_filter_fixture();
+        fs.set_dir_entries(
+            "/root/quiet",
+            vec![
+                file_with_metadata("/root/quiet/tiny", 1, 300),
+                FsEntry {
+                    path: PathBuf::from("/root/quiet/deep"),
+                    name: "deep".to_owned(),
+                    kind: EntryKind::Directory,
+                    metadata: None,
+                },
+                FsEntry {
+                    path: PathBuf::from("/root/quiet/empty"),
+                    name: "empty".to_owned(),
+                    kind: EntryKind::Directory,
+                    metadata: None,
+                },
+            ],
+        );
+        fs.set_dir_entries(
+            "/root/quiet/deep",
+            vec![file_with_metadata("/root/quiet/deep/disk.img", 8192, 100)],
+        );
+        fs.set_dir_entries(
+            "/root/quiet/empty",
+            vec![file_with_metadata("/root/quiet/empty/note", 1, 100)],
+        );
+
+        let options = WalkOptions {
+            dirs_only: true,
+            min_size: Some(1024),
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir(&fs, Path::new("/root"), &options).await.unwrap();
+        let paths: Vec<String> = tree
+            .iter_depth_first()
+            .map(|item| item.path.display().to_string())
+            .collect();
+        assert_eq!(paths, vec!["nested", "quiet", "quiet/deep"]);
+        assert_eq!(
+            walk_dir_blocking(&fs, Path::new("/root"), &options).unwrap(),
+            tree
+        );
+        assert_eq!(
+            collect_events(&fs, &options).await,
+            vec![
+                "0:nested/:false:enter",
+                "leave",
+                "0:quiet/:true:enter",
+                "1:deep/:true:enter",
+                "leave",
+                "leave",
+            ]
+        );
+
+        // Hidden files must still pass the other filters
+        let options = WalkOptions {
+            ignore_pattern: Some("*.iso|*.img".to_owned()),
+            ..options
+        };
+        let tree = walk_dir(&fs, Path::new("/root"), &options).await.unwrap();
+        assert!(tree.children.is_empty());
+    }
+
+    #[tokio::test]
+    async fn modification_time_filters_are_exclusive_bounds() {
+        let fs = metadata_filter_fixture();
+
+        let options = WalkOptions {
+            newer_than: Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(100)),
+            older_than: Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(300)),
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir(&fs, Path::new("/root"), &options).await.unwrap();
+        let names: Vec<&str> = tree.children.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["big.bin"]);
+    }
+
+    #[tokio::test]
+    async fn metadata_filters_exclude_entries_without_metadata() {
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![FsEntry {
+                path: PathBuf::from("/root/unknown"),
+                name: "unknown".to_owned(),
+                kind: EntryKind::File,
+                metadata: None,
+            }],
+        );
+
+        let options = WalkOptions {
+            max_size: Some(u64::MAX),
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir(&fs, Path::new("/root"), &options).await.unwrap();
+        assert!(tree.children.is_empty());
+    }
+
+    // --- File limit tests ---
+
+    #[tokio::test]
+    async fn file_limit_leaves_large_directories_unopened() {
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![
+                FsEntry {
+                    path: PathBuf::from("/root/cache"),
+                    name: "cache".to_owned(),
+                    kind: EntryKind::Directory,
+                    metadata: None,
+                },
+                FsEntry {
+                    path: PathBuf::from("/root/src"),
+                    name: "src".to_owned(),
+                    kind: EntryKind::Directory,
+                    metadata: None,
+                },
+            ],
+        );
+        fs.set_dir_entries(
+            "/root/cache",
+            (0..3)
+                .map(|i| FsEntry {
+                    path: PathBuf::from(format!("/root/cache/{i}")),
+                    name: i.to_string(),
+                    kind: EntryKind::File,
+                    metadata: None,
+                })
+                .collect(),
+        );
+        fs.set_dir_entries(
+            "/root/src",
+            vec![FsEntry {
+                path: PathBuf::from("/root/src/lib.rs"),
+                name: "lib.rs".to_owned(),
+                kind: EntryKind::File,
+                metadata: None,
+            }],
+        );
+
+        let options = WalkOptions {
+            file_limit: Some(2),
+            ..WalkOptions::default()
+        };
+        let tree = walk_dir(&fs, Path::new("/root"), &options).await.unwrap();
+        assert_eq!(tree.exceeded_filelimit, None);
+        assert_eq!(tree.children[0].name, "cache/");
+        assert_eq!(tree.children[0].exceeded_filelimit, Some(3));
+        assert!(tree.children[0].children.is_empty());
+        assert_eq!(tree.children[1].exceeded_filelimit, None);
+        assert_eq!(tree.children[1].children.len(), 1);
+    }
+
+    // --- Streaming walk tests ---
+
+    async fn collect_events(fs: &MockFileSystem, options: &WalkOptions) -> Vec<String> {
+        let mut walker = walk_dir_streaming(fs, Path::new("/root"), options)
+            .await
+            .unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = walker.next().await {
+            events.push(match event {
+                WalkEvent::Entry(entry) => {
+                    format!("{}:{}:{}", entry.depth, entry.name, entry.is_last)
+                }
+                WalkEvent::EnterDir(entry) => {
+                    format!("{}:{}:{}:enter", entry.depth, entry.name, entry.is_last)
+                }
+                WalkEvent::LeaveDir => "leave".to_owned(),
+                WalkEvent::Error(error) => format!("error:{error}"),
+            });
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn streaming_walk_yields_events_in_display_order() {
+        let fs = type_filter_fixture();
+
+        let events = collect_events(&fs, &WalkOptions::default()).await;
+        assert_eq!(
+            events,
+            vec![
+                "0:empty/:false:enter",
+                "leave",
+                "0:file:false",
+                "0:sub/:true:enter",
+                "1:link:false",
+                "1:other:true",
+                "leave",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn streaming_walk_reports_unreadable_directories_inline() {
+        let fs = MockFileSystem::default();
+        fs.set_dir_entries(
+            "/root",
+            vec![FsEntry {
+                path: PathBuf::from("/root/secret"),
+                name: "secret".to_owned(),
+                kind: EntryKind::Directory,
+                metadata: None,
+            }],
+        );
+        fs.set_error("/root/secret", "Permission denied");
+
+        let options = WalkOptions::default();
+        let mut walker = walk_dir_streaming(&fs, Path::new("/root"), &options)
+            .await
+            .unwrap();
+        assert_eq!(walker.root_error(), None);
+        match walker.next().await {
+            Some(WalkEvent::Entry(entry)) => {
+                assert_eq!(entry.name, "secret/");
+                assert_eq!(
+                    entry.error.map(|error| error.to_string()).as_deref(),
+                    Some("Permission denied")
+                );
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+        assert_eq!(
+            walker.next().await,
+            Some(WalkEvent::Error(Error::Io {
+                path: PathBuf::from("/root/secret"),
+                kind: std::io::ErrorKind::Other,
+                message: "Permission denied".to_owned(),
+            }))
+        );
+        assert_eq!(walker.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn stream_yields_root_error_first() {
+        use futures_util::StreamExt;
+
+        let fs = MockFileSystem::default();
+        fs.set_error("/root", "Permission denied");
+
+        let options = WalkOptions::default();
+        let events: Vec<WalkEvent> = walk_dir_stream(&fs, Path::new("/root"), &options)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            vec![WalkEvent::Error(Error::Io {
+                path: PathBuf::from("/root"),
+                kind: std::io::ErrorKind::Other,
+                message: "Permission denied".to_owned(),
+            })]
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_honors_walk_options() {
+        use futures_util::StreamExt;
+
+        let fs = type_filter_fixture();
+
+        let options = WalkOptions {
+            entry_types: Some(HashSet::from([EntryKind::Symlink])),
+            prune: true,
+            ..WalkOptions::default()
+        };
+        let paths: Vec<PathBuf> = walk_dir_stream(&fs, Path::new("/root"), &options)
+            .await
+            .unwrap()
+            .filter_map(|event| async move {
+                match event {
+                    WalkEvent::Entry(entry) | WalkEvent::EnterDir(entry) => Some(entry.path),
+                    _ => None,
+                }
+            })
+            .collect()
+            .await;
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("/root/sub"), PathBuf::from("/root/sub/link")]
+        );
+    }
+
+    #[tokio::test]
+    async fn streaming_walk_prunes_before_yielding_siblings() {
+        let fs = type_filter_fixture();
+
+        let options = WalkOptions {
+            entry_types: Some(HashSet::from([EntryKind::File])),
+            prune: true,
+            ..WalkOptions::default()
+        };
+        let events = collect_events(&fs, &options).await;
+        assert_eq!(
+            events,
+            vec!["0:file:false", "0:sub/:true:enter", "1:other:true", "leave"]
+        );
+    }
+
+    #[tokio::test]
+    async fn pruning_reads_each_directory_once() {
+        let entry = |path: &str, kind| FsEntry {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap().to_owned(),
+            kind,
+            metadata: None,
+        };
+        let fs = MockFileSystem::default();
+        let chain = [
+            "/root",
+            "/root/a",
+            "/root/a/b",
+            "/root/a/b/c",
+            "/root/a/b/c/d",
+        ];
+        for pair in chain.windows(2) {
+            fs.set_dir_entries(pair[0], vec![entry(pair[1], EntryKind::Directory)]);
+        }
+        fs.set_dir_entries(
+            "/root/a/b/c/d",
+            vec![
+                entry("/root/a/b/c/d/empty", EntryKind::Directory),
+                entry("/root/a/b/c/d/file", EntryKind::File),
+            ],
+        );
+        fs.set_dir_entries("/root/a/b/c/d/empty", vec![]);
+
+        let options = WalkOptions {
+            prune: true,
+            ..WalkOptions::default()
+        };
+        let events = collect_events(&fs, &options).await;
+        assert_eq!(
+            events,
+            vec![
+                "0:a/:true:enter",
+                "1:b/:true:enter",
+                "2:c/:true:enter",
+                "3:d/:true:enter",
+                "4:file:true",
+                "leave",
+                "leave",
+                "leave",
+                "leave",
+            ]
+        );
+
+        let mut calls = fs.calls();
+        calls.sort();
+        let mut expected: Vec<PathBuf> = chain.iter().map(PathBuf::from).collect();
+        expected.push(PathBuf::from("/root/a/b/c/d/empty"));
+        expected.sort();
+        assert_eq!(calls, expected);
+    }
+
+    #[tokio::test]
+    async fn skipped_siblings_are_not_read() {
+        let fs = type_filter_fixture();
+
+        let options = WalkOptions::default();
+        let mut walker = walk_dir_streaming(&fs, Path::new("/root"), &options)
+            .await
+            .unwrap();
+        assert!(matches!(walker.next().await, Some(WalkEvent::EnterDir(_))));
+        assert_eq!(walker.next().await, Some(WalkEvent::LeaveDir));
+        assert_eq!(walker.skip_remaining_siblings(), 2);
+        assert_eq!(walker.next().await, None);
+        assert!(!fs.calls().contains(&PathBuf::from("/root/sub")));
+    }
+
     // --- Dirs first tests ---
 
     #[tokio::test]
@@ -573,16 +1901,19 @@ mod tests {
                     path: PathBuf::from("/root/zebra.txt"),
                     name: "zebra.txt".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/alpha"),
                     name: "alpha".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/beta.txt"),
                     name: "beta.txt".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
             ],
         );
@@ -608,21 +1939,25 @@ mod tests {
                     path: PathBuf::from("/root/zdir"),
                     name: "zdir".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/adir"),
                     name: "adir".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/zfile"),
                     name: "zfile".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/afile"),
                     name: "afile".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
             ],
         );
@@ -651,11 +1986,13 @@ mod tests {
                     path: PathBuf::from("/root/.hidden"),
                     name: ".hidden".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/visible"),
                     name: "visible".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
             ],
         );
@@ -675,16 +2012,19 @@ mod tests {
                     path: PathBuf::from("/root/.hidden"),
                     name: ".hidden".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/.gitignore"),
                     name: ".gitignore".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/visible"),
                     name: "visible".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
             ],
         );
@@ -710,16 +2050,19 @@ mod tests {
                     path: PathBuf::from("/root/src"),
                     name: "src".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/node_modules"),
                     name: "node_modules".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/README.md"),
                     name: "README.md".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
             ],
         );
@@ -746,11 +2089,13 @@ mod tests {
                     path: PathBuf::from("/root/dir1"),
                     name: "dir1".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/file1"),
                     name: "file1".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
             ],
         );
@@ -761,11 +2106,13 @@ mod tests {
                     path: PathBuf::from("/root/dir1/subdir"),
                     name: "subdir".to_owned(),
                     kind: EntryKind::Directory,
+                    metadata: None,
                 },
                 FsEntry {
                     path: PathBuf::from("/root/dir1/file2"),
                     name: "file2".to_owned(),
                     kind: EntryKind::File,
+                    metadata: None,
                 },
             ],
         );
@@ -0,0 +1,109 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Errors produced by rtree's library API.
+///
+/// The `Display` output matches what the CLI prints inline, e.g.
+/// `[error: Permission denied (os error 13)]`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum Error {
+    /// A directory could not be read
+    Io {
+        path: PathBuf,
+        #[cfg_attr(feature = "serde", serde(with = "io_kind"))]
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// An ignore pattern could not be compiled
+    InvalidPattern { pattern: String, message: String },
+    /// A serialized or textual tree could not be loaded
+    InvalidTree { message: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Wrap an I/O error that occurred while reading `path`.
+    pub fn io(path: impl Into<PathBuf>, err: &io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            kind: err.kind(),
+            message: err.to_string(),
+        }
+    }
+
+    /// The I/O error kind, if this is an I/O error.
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            Error::Io { kind, .. } => Some(*kind),
+            Error::InvalidPattern { .. } | Error::InvalidTree { .. } => None,
+        }
+    }
+
+    /// The path the error relates to, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::Io { path, .. } => Some(path),
+            Error::InvalidPattern { .. } | Error::InvalidTree { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { message, .. } => f.write_str(message),
+            Error::InvalidPattern { pattern, .. } => {
+                write!(f, "Invalid ignore pattern: {pattern}")
+            }
+            Error::InvalidTree { message } => write!(f, "Invalid tree: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Serialize `io::ErrorKind` as a stable snake_case name. Kinds without a
+/// stable name are written as `other`, and unknown names read back as `Other`.
+#[cfg(feature = "serde")]
+mod io_kind {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::io::ErrorKind;
+
+    const KINDS: &[(ErrorKind, &str)] = &[
+        (ErrorKind::NotFound, "not_found"),
+        (ErrorKind::PermissionDenied, "permission_denied"),
+        (ErrorKind::AlreadyExists, "already_exists"),
+        (ErrorKind::NotADirectory, "not_a_directory"),
+        (ErrorKind::IsADirectory, "is_a_directory"),
+        (ErrorKind::DirectoryNotEmpty, "directory_not_empty"),
+        (ErrorKind::ReadOnlyFilesystem, "read_only_filesystem"),
+        (ErrorKind::InvalidInput, "invalid_input"),
+        (ErrorKind::InvalidData, "invalid_data"),
+        (ErrorKind::TimedOut, "timed_out"),
+        (ErrorKind::Interrupted, "interrupted"),
+        (ErrorKind::Unsupported, "unsupported"),
+        (ErrorKind::UnexpectedEof, "unexpected_eof"),
+        (ErrorKind::OutOfMemory, "out_of_memory"),
+        (ErrorKind::Other, "other"),
+    ];
+
+    pub fn serialize<S: Serializer>(kind: &ErrorKind, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = KINDS
+            .iter()
+            .find(|(known, _)| known == kind)
+            .map_or("other", |(_, name)| name);
+        serializer.serialize_str(name)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ErrorKind, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(KINDS
+            .iter()
+            .find(|(_, known)| *known == name)
+            .map_or(ErrorKind::Other, |(kind, _)| *kind))
+    }
+}
@@ -0,0 +1,255 @@
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::models::{EntryMetadata, FsEntry};
+use crate::{Error, Result};
+
+#[cfg(feature = "async")]
+use super::FileSystem;
+use super::{BlockingFileSystem, PathListFileSystem, TarFileSystem, ZipFileSystem};
+
+pub(super) const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+pub(super) const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const EMPTY_ZIP_MAGIC: &[u8] = b"PK\x05\x06";
+/// Offset of the `ustar` magic in a POSIX tar header
+const USTAR_OFFSET: usize = 257;
+/// Enough of a file to recognize any supported format
+const SNIFF_LEN: usize = USTAR_OFFSET + 5;
+
+/// Names of compressed tarballs; gzip and zstd data alone could be anything.
+const COMPRESSED_TAR_SUFFIXES: &[&str] = &[".tar.gz", ".tgz", ".crate", ".tar.zst", ".tzst"];
+/// Names worth opening when descending into nested archives.
+const ARCHIVE_SUFFIXES: &[&str] = &[
+    ".tar", ".tar.gz", ".tgz", ".crate", ".tar.zst", ".tzst", ".zip", ".jar", ".war", ".ear",
+    ".whl", ".egg", ".nupkg", ".apk", ".aar", ".vsix",
+];
+
+/// Levels of nested archives opened by default, enough for a jar in a war in an ear.
+const DEFAULT_MAX_NESTING: usize = 4;
+/// Largest archive member read into memory by default.
+const DEFAULT_MAX_MEMBER_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Options shared by the archive-backed filesystems.
+#[derive(Debug, Clone)]
+pub struct ArchiveOptions {
+    /// Show archives found inside the archive as directories of their contents
+    pub nested: bool,
+    /// How many levels of archives inside archives to open; deeper ones are
+    /// listed as files, so an archive containing itself cannot recurse forever
+    pub max_nesting: usize,
+    /// Largest member, in bytes, read to open it as a nested archive or to
+    /// get a symlink target; larger nested archives are listed as files
+    pub max_member_size: u64,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        ArchiveOptions {
+            nested: false,
+            max_nesting: DEFAULT_MAX_NESTING,
+            max_member_size: DEFAULT_MAX_MEMBER_SIZE,
+        }
+    }
+}
+
+/// An archive format recognized from its leading bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArchiveFormat {
+    /// tar, possibly gzip or zstd compressed
+    Tar,
+    /// zip and zip-based formats such as jar and wheel
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Sniff the format of the file at `path`; `None` if it is not an archive.
+    pub fn detect(path: &Path) -> io::Result<Option<Self>> {
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        File::open(path)?
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut head)?;
+        Ok(Self::from_bytes(&head, &path.to_string_lossy()))
+    }
+
+    /// Recognize a format from the start of the data and the file name.
+    pub fn from_bytes(head: &[u8], name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if head.starts_with(ZIP_MAGIC) || head.starts_with(EMPTY_ZIP_MAGIC) {
+            Some(ArchiveFormat::Zip)
+        } else if head.starts_with(GZIP_MAGIC) || head.starts_with(ZSTD_MAGIC) {
+            COMPRESSED_TAR_SUFFIXES
+                .iter()
+                .any(|suffix| name.ends_with(suffix))
+                .then_some(ArchiveFormat::Tar)
+        } else if head.get(USTAR_OFFSET..SNIFF_LEN) == Some(b"ustar") || name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether a file inside an archive should be opened as a nested archive.
+pub(crate) fn is_nested_candidate(name: &str, size: u64, options: &ArchiveOptions) -> bool {
+    let name = name.to_ascii_lowercase();
+    options.nested
+        && options.max_nesting > 0
+        && size <= options.max_member_size
+        && ARCHIVE_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// Read a member's data, or `None` if it turns out to be larger than
+/// `options.max_member_size`, whatever size its header claimed.
+pub(crate) fn read_member<R: Read>(
+    reader: R,
+    options: &ArchiveOptions,
+) -> io::Result<Option<Vec<u8>>> {
+    let mut data = Vec::new();
+    reader
+        .take(options.max_member_size.saturating_add(1))
+        .read_to_end(&mut data)?;
+    Ok((data.len() as u64 <= options.max_member_size).then_some(data))
+}
+
+/// Mount the archive in `data` at `path` inside `entries`. Returns false,
+/// leaving `entries` untouched, if the data is not a readable archive.
+pub(crate) fn mount_nested(
+    entries: &mut PathListFileSystem,
+    path: &str,
+    data: &[u8],
+    metadata: Option<EntryMetadata>,
+    options: &ArchiveOptions,
+) -> bool {
+    // Archives inside this one are a level deeper
+    let options = &ArchiveOptions {
+        max_nesting: options.max_nesting.saturating_sub(1),
+        ..options.clone()
+    };
+    let nested = match ArchiveFormat::from_bytes(data, path) {
+        Some(ArchiveFormat::Tar) => TarFileSystem::build(data, options).ok(),
+        Some(ArchiveFormat::Zip) => ZipFileSystem::build(io::Cursor::new(data), options).ok(),
+        None => None,
+    };
+    match nested {
+        Some(nested) => {
+            entries.graft(path, &nested, metadata);
+            true
+        }
+        None => false,
+    }
+}
+
+/// A filesystem over any supported archive, chosen by sniffing the file.
+#[derive(Clone, Debug)]
+pub enum ArchiveFileSystem {
+    Tar(TarFileSystem),
+    Zip(ZipFileSystem),
+}
+
+impl ArchiveFileSystem {
+    /// Open `path` as an archive, or return `None` if it is not one.
+    pub fn open(path: &Path, options: &ArchiveOptions) -> Result<Option<Self>> {
+        let format = ArchiveFormat::detect(path).map_err(|err| Error::io(path, &err))?;
+        Ok(match format {
+            Some(ArchiveFormat::Tar) => Some(ArchiveFileSystem::Tar(
+                TarFileSystem::open_with_options(path, options)?,
+            )),
+            Some(ArchiveFormat::Zip) => Some(ArchiveFileSystem::Zip(
+                ZipFileSystem::open_with_options(path, options)?,
+            )),
+            None => None,
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl FileSystem for ArchiveFileSystem {
+    async fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        BlockingFileSystem::read_dir(self, dir)
+    }
+}
+
+impl BlockingFileSystem for ArchiveFileSystem {
+    fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        match self {
+            ArchiveFileSystem::Tar(fs) => BlockingFileSystem::read_dir(fs, dir),
+            ArchiveFileSystem::Zip(fs) => BlockingFileSystem::read_dir(fs, dir),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats_from_magic_and_name() {
+        let mut tar_header = vec![0u8; 512];
+        tar_header[USTAR_OFFSET..SNIFF_LEN].copy_from_slice(b"ustar");
+
+        assert_eq!(
+            ArchiveFormat::from_bytes(b"PK\x03\x04rest", "lib.jar"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            ArchiveFormat::from_bytes(&tar_header, "data.bin"),
+            Some(ArchiveFormat::Tar)
+        );
+        assert_eq!(
+            ArchiveFormat::from_bytes(&[0x1f, 0x8b, 0x08], "release.TGZ"),
+            Some(ArchiveFormat::Tar)
+        );
+        // A gzipped file that is not a tarball
+        assert_eq!(
+            ArchiveFormat::from_bytes(&[0x1f, 0x8b, 0x08], "log.gz"),
+            None
+        );
+        assert_eq!(ArchiveFormat::from_bytes(b"plain text", "notes.txt"), None);
+    }
+
+    #[test]
+    fn nested_candidates_require_the_option() {
+        let nested = ArchiveOptions {
+            nested: true,
+            ..ArchiveOptions::default()
+        };
+        assert!(is_nested_candidate("lib/Foo.JAR", 10, &nested));
+        assert!(!is_nested_candidate("lib/Foo.class", 10, &nested));
+        assert!(!is_nested_candidate(
+            "lib/Foo.jar",
+            10,
+            &ArchiveOptions::default()
+        ));
+    }
+
+    #[test]
+    fn nested_candidates_respect_depth_and_size_limits() {
+        let limited = ArchiveOptions {
+            nested: true,
+            max_nesting: 1,
+            max_member_size: 100,
+        };
+        assert!(is_nested_candidate("lib/Foo.jar", 100, &limited));
+        assert!(!is_nested_candidate("lib/Foo.jar", 101, &limited));
+        assert!(!is_nested_candidate(
+            "lib/Foo.jar",
+            10,
+            &ArchiveOptions {
+                max_nesting: 0,
+                ..limited.clone()
+            }
+        ));
+
+        // Headers can understate a member's size
+        assert_eq!(
+            read_member(&[7u8; 100][..], &limited).unwrap(),
+            Some(vec![7; 100])
+        );
+        assert_eq!(read_member(&[7u8; 101][..], &limited).unwrap(), None);
+    }
+}
@@ -0,0 +1,466 @@
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use git2::{
+    ErrorCode, ObjectType, Repository, Status, StatusOptions, TreeWalkMode, TreeWalkResult,
+};
+
+use crate::core::transform::annotate_status;
+use crate::models::{DirTree, EntryKind, EntryMetadata, FsEntry, GitStatus};
+use crate::{Error, Result};
+
+#[cfg(feature = "async")]
+use super::FileSystem;
+use super::{BlockingFileSystem, PathListFileSystem};
+
+/// Git file modes of tree entries
+const MODE_EXECUTABLE: i32 = 0o100_755;
+const MODE_SYMLINK: i32 = 0o120_000;
+const MODE_SUBMODULE: i32 = 0o160_000;
+
+/// A read-only filesystem over the tree of a commit, branch or tag in a
+/// local git repository, read from the object database without touching
+/// the working copy.
+///
+/// Files carry their blob size and `0o644`/`0o755` mode, symlinks their
+/// target, and submodules appear as empty directories, as in a checkout
+/// that has not initialized them. Like [`PathListFileSystem`], the root is
+/// the empty path.
+#[derive(Clone, Debug)]
+pub struct GitFileSystem {
+    entries: PathListFileSystem,
+}
+
+impl GitFileSystem {
+    /// Read the tree of `rev` (anything `git rev-parse` accepts) in the
+    /// repository containing `path`. When `path` is a subdirectory of the
+    /// work tree, only that directory is exposed.
+    pub fn open(path: impl AsRef<Path>, rev: &str) -> Result<Self> {
+        let path = path.as_ref();
+        let repo = Repository::discover(path).map_err(|err| git_error(path, &err))?;
+        let tree = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|err| git_error(path, &err))?;
+
+        let tree = match subdirectory(&repo, path) {
+            Some(subdir) if !subdir.as_os_str().is_empty() => tree
+                .get_path(&subdir)
+                .and_then(|entry| entry.to_object(&repo))
+                .and_then(|object| object.peel_to_tree())
+                .map_err(|err| git_error(path, &err))?,
+            _ => tree,
+        };
+
+        Self::build(&repo, &tree)
+            .map(|entries| GitFileSystem { entries })
+            .map_err(|err| git_error(path, &err))
+    }
+
+    fn build(
+        repo: &Repository,
+        tree: &git2::Tree,
+    ) -> std::result::Result<PathListFileSystem, git2::Error> {
+        let odb = repo.odb()?;
+        let mut entries = PathListFileSystem::default();
+        let mut failure = None;
+
+        tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+            let Some(name) = entry.name() else {
+                return TreeWalkResult::Skip;
+            };
+            let path = format!("{dir}{name}");
+            let mode = entry.filemode();
+
+            let (kind, metadata) = match entry.kind() {
+                Some(ObjectType::Tree) => (EntryKind::Directory, None),
+                _ if mode == MODE_SUBMODULE => (EntryKind::Directory, None),
+                _ => {
+                    let size = match odb.read_header(entry.id()) {
+                        Ok((size, _)) => size as u64,
+                        Err(err) => {
+                            failure = Some(err);
+                            return TreeWalkResult::Abort;
+                        }
+                    };
+                    let link_target = if mode == MODE_SYMLINK {
+                        match repo.find_blob(entry.id()) {
+                            Ok(blob) => Some(PathBuf::from(
+                                String::from_utf8_lossy(blob.content()).into_owned(),
+                            )),
+                            Err(err) => {
+                                failure = Some(err);
+                                return TreeWalkResult::Abort;
+                            }
+                        }
+                    } else {
+                        None
+                    };
+                    let (kind, permissions) = match mode {
+                        MODE_SYMLINK => (EntryKind::Symlink, 0o777),
+                        MODE_EXECUTABLE => (EntryKind::File, 0o755),
+                        _ => (EntryKind::File, 0o644),
+                    };
+                    let metadata = EntryMetadata {
+                        size,
+                        modified: None,
+                        mode: Some(permissions),
+                        link_target,
+                    };
+                    (kind, Some(metadata))
+                }
+            };
+
+            entries.insert(&path, kind, metadata);
+            TreeWalkResult::Ok
+        })?;
+
+        match failure {
+            Some(err) => Err(err),
+            None => Ok(entries),
+        }
+    }
+}
+
+/// The `git status` of everything below a directory of a working copy.
+///
+/// Untracked directories are listed file by file, while an ignored directory
+/// is reported once and covers everything inside it. A directory takes the
+/// most significant status of its contents; ignored contents do not count.
+#[derive(Clone, Debug, Default)]
+pub struct RepoStatus {
+    /// Changed, untracked and ignored paths, relative to the directory
+    entries: BTreeMap<PathBuf, GitStatus>,
+    /// Status of the directory itself when it lies inside an ignored one
+    inherited: Option<GitStatus>,
+}
+
+impl RepoStatus {
+    /// Read the index and working tree state of the repository containing
+    /// `path`, for the files below `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let repo = Repository::discover(path).map_err(|err| git_error(path, &err))?;
+        if repo.workdir().is_none() {
+            return Err(Error::io(
+                path,
+                &io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "bare repository has no working copy",
+                ),
+            ));
+        }
+        let prefix = subdirectory(&repo, path).unwrap_or_default();
+
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(true)
+            .recurse_ignored_dirs(false);
+        let statuses = repo
+            .statuses(Some(&mut options))
+            .map_err(|err| git_error(path, &err))?;
+
+        let mut status = RepoStatus::default();
+        for entry in statuses.iter() {
+            let (Some(entry_path), Some(kind)) = (entry.path(), classify(entry.status())) else {
+                continue;
+            };
+            let entry_path = Path::new(entry_path.trim_end_matches('/'));
+            match entry_path.strip_prefix(&prefix) {
+                Ok(relative) => {
+                    status.entries.insert(relative.to_path_buf(), kind);
+                }
+                Err(_) if prefix.starts_with(entry_path) => status.inherited = Some(kind),
+                Err(_) => {}
+            }
+        }
+        Ok(status)
+    }
+
+    /// The status of the entry at `path`, relative to the directory this was
+    /// opened for.
+    pub fn status_of(&self, path: &Path) -> Option<GitStatus> {
+        if self.inherited.is_some() {
+            return self.inherited;
+        }
+        // The entry itself, or an ignored directory containing it
+        if let Some(status) = path
+            .ancestors()
+            .find_map(|ancestor| self.entries.get(ancestor))
+        {
+            return Some(*status);
+        }
+        // Paths order component-wise, so a directory's contents follow it
+        self.entries
+            .range(path.to_path_buf()..)
+            .take_while(|(entry_path, _)| entry_path.starts_with(path))
+            .map(|(_, status)| *status)
+            .filter(|status| *status != GitStatus::Ignored)
+            .min()
+    }
+
+    /// Set the status of every node in `tree`, which must be rooted at the
+    /// directory this was opened for.
+    pub fn annotate(&self, tree: &mut DirTree) {
+        annotate_status(tree, |path, _| self.status_of(path));
+    }
+}
+
+/// Reduce libgit2's index and working tree flags to a single status.
+fn classify(status: Status) -> Option<GitStatus> {
+    if status.is_ignored() {
+        Some(GitStatus::Ignored)
+    } else if status.is_index_new() {
+        Some(GitStatus::Added)
+    } else if status.is_wt_new() {
+        Some(GitStatus::Untracked)
+    } else if status.is_empty() {
+        None
+    } else {
+        Some(GitStatus::Modified)
+    }
+}
+
+/// The location of `path` relative to the repository's work tree, if it has one.
+fn subdirectory(repo: &Repository, path: &Path) -> Option<PathBuf> {
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let path = path.canonicalize().ok()?;
+    path.strip_prefix(workdir).ok().map(Path::to_path_buf)
+}
+
+fn git_error(path: &Path, err: &git2::Error) -> Error {
+    let kind = match err.code() {
+        ErrorCode::NotFound => io::ErrorKind::NotFound,
+        _ => io::ErrorKind::Other,
+    };
+    Error::io(path, &io::Error::new(kind, err.message()))
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl FileSystem for GitFileSystem {
+    async fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        BlockingFileSystem::read_dir(&self.entries, dir)
+    }
+}
+
+impl BlockingFileSystem for GitFileSystem {
+    fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        BlockingFileSystem::read_dir(&self.entries, dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::render::write_children;
+    use crate::core::walk::{WalkOptions, walk_dir_blocking};
+    use git2::{Oid, Signature};
+    use tempfile::TempDir;
+
+    fn render(fs: &GitFileSystem) -> String {
+        let tree = walk_dir_blocking(fs, Path::new(""), &WalkOptions::default()).unwrap();
+        let mut out = Vec::new();
+        write_children(&mut out, &tree.children).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Commit a tree built from `(path, mode, contents)` entries onto HEAD.
+    fn commit(repo: &Repository, files: &[(&str, i32, &[u8])], message: &str) -> Oid {
+        let mut index = git2::Index::new().unwrap();
+        for (path, mode, contents) in files {
+            let id = if *mode == MODE_SUBMODULE {
+                Oid::from_str(std::str::from_utf8(contents).unwrap()).unwrap()
+            } else {
+                repo.blob(contents).unwrap()
+            };
+            let entry = git2::IndexEntry {
+                ctime: git2::IndexTime::new(0, 0),
+                mtime: git2::IndexTime::new(0, 0),
+                dev: 0,
+                ino: 0,
+                mode: *mode as u32,
+                uid: 0,
+                gid: 0,
+                file_size: 0,
+                id,
+                flags: 0,
+                flags_extended: 0,
+                path: path.as_bytes().to_vec(),
+            };
+            index.add(&entry).unwrap();
+        }
+        let tree = repo.find_tree(index.write_tree_to(repo).unwrap()).unwrap();
+        let signature = Signature::now("rtree", "rtree@example.com").unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_the_tree_of_a_revision() {
+        let temp = TempDir::new().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let first = commit(&repo, &[("README.md", 0o100_644, b"old\n")], "first");
+        repo.tag_lightweight("v1", &repo.find_object(first, None).unwrap(), false)
+            .unwrap();
+        commit(
+            &repo,
+            &[
+                ("README.md", 0o100_644, b"new readme\n"),
+                ("bin/run", MODE_EXECUTABLE, b"#!/bin/sh\n"),
+                ("latest", MODE_SYMLINK, b"bin/run"),
+                ("vendor/lib", MODE_SUBMODULE, first.to_string().as_bytes()),
+            ],
+            "second",
+        );
+
+        let fs = GitFileSystem::open(temp.path(), "HEAD").unwrap();
+        assert_eq!(
+            render(&fs),
+            concat!(
+                "|-- README.md\n",
+                "|-- bin/\n",
+                "|   `-- run\n",
+                "|-- latest\n",
+                "`-- vendor/\n",
+                "    `-- lib/\n",
+            )
+        );
+
+        let root = BlockingFileSystem::read_dir(&fs, Path::new("")).unwrap();
+        let readme = root.iter().find(|entry| entry.name == "README.md").unwrap();
+        assert_eq!(readme.metadata.as_ref().unwrap().size, 11);
+        let latest = root.iter().find(|entry| entry.name == "latest").unwrap();
+        assert_eq!(latest.kind, EntryKind::Symlink);
+        assert_eq!(
+            latest.metadata.as_ref().unwrap().link_target,
+            Some(PathBuf::from("bin/run"))
+        );
+        let bin = BlockingFileSystem::read_dir(&fs, Path::new("bin")).unwrap();
+        assert_eq!(bin[0].metadata.as_ref().unwrap().mode, Some(0o755));
+
+        let old = GitFileSystem::open(temp.path(), "v1").unwrap();
+        assert_eq!(render(&old), "`-- README.md\n");
+    }
+
+    #[test]
+    fn subdirectory_paths_show_that_directory() {
+        let temp = TempDir::new().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        commit(
+            &repo,
+            &[
+                ("src/lib.rs", 0o100_644, b""),
+                ("src/fs/mod.rs", 0o100_644, b""),
+                ("Cargo.toml", 0o100_644, b""),
+            ],
+            "init",
+        );
+        std::fs::create_dir(temp.path().join("src")).unwrap();
+
+        let fs = GitFileSystem::open(temp.path().join("src"), "HEAD").unwrap();
+        assert_eq!(render(&fs), "|-- fs/\n|   `-- mod.rs\n`-- lib.rs\n");
+    }
+
+    #[test]
+    fn status_marks_changes_and_rolls_them_up() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        let repo = Repository::init(root).unwrap();
+        let files: &[(&str, i32, &[u8])] = &[
+            (".gitignore", 0o100_644, b"target/\n*.log\n"),
+            ("src/lib.rs", 0o100_644, b""),
+            ("src/main.rs", 0o100_644, b""),
+            ("docs/guide.md", 0o100_644, b""),
+        ];
+        commit(&repo, files, "init");
+        for (path, _, contents) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index
+            .read_tree(&repo.head().unwrap().peel_to_tree().unwrap())
+            .unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("src/new.rs"), "").unwrap();
+        index.add_path(Path::new("src/new.rs")).unwrap();
+        index.write().unwrap();
+        std::fs::create_dir_all(root.join("scratch/notes")).unwrap();
+        std::fs::write(root.join("scratch/notes/todo.txt"), "").unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::write(root.join("target/debug/app"), "").unwrap();
+        std::fs::write(root.join("docs/build.log"), "").unwrap();
+
+        let mut tree = walk_dir_blocking(
+            &crate::fs::RealFileSystem::new(),
+            root,
+            &WalkOptions::default(),
+        )
+        .unwrap();
+        tree.children.retain(|node| node.name != ".git/");
+        RepoStatus::open(root).unwrap().annotate(&mut tree);
+        let mut out = Vec::new();
+        write_children(&mut out, &tree.children).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "|-- .gitignore\n",
+                "|-- docs/\n",
+                "|   |-- build.log [!!]\n",
+                "|   `-- guide.md\n",
+                "|-- scratch/ [??]\n",
+                "|   `-- notes/ [??]\n",
+                "|       `-- todo.txt [??]\n",
+                "|-- src/ [M]\n",
+                "|   |-- lib.rs\n",
+                "|   |-- main.rs [M]\n",
+                "|   `-- new.rs [A]\n",
+                "`-- target/ [!!]\n",
+                "    `-- debug/ [!!]\n",
+                "        `-- app [!!]\n",
+            )
+        );
+
+        let inside_src = RepoStatus::open(root.join("src")).unwrap();
+        assert_eq!(
+            inside_src.status_of(Path::new("new.rs")),
+            Some(GitStatus::Added)
+        );
+        assert_eq!(
+            inside_src.status_of(Path::new("")),
+            Some(GitStatus::Modified)
+        );
+        let inside_target = RepoStatus::open(root.join("target/debug")).unwrap();
+        assert_eq!(
+            inside_target.status_of(Path::new("app")),
+            Some(GitStatus::Ignored)
+        );
+    }
+
+    #[test]
+    fn unknown_revisions_are_not_found() {
+        let temp = TempDir::new().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        commit(&repo, &[("a", 0o100_644, b"")], "init");
+
+        let err = GitFileSystem::open(temp.path(), "no-such-tag").unwrap_err();
+        assert_eq!(err.io_kind(), Some(io::ErrorKind::NotFound));
+        assert_eq!(err.path(), Some(temp.path()));
+    }
+}
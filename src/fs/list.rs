@@ -0,0 +1,228 @@
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::models::{EntryKind, EntryMetadata, FsEntry};
+use crate::{Error, Result};
+
+use super::BlockingFileSystem;
+#[cfg(feature = "async")]
+use super::FileSystem;
+
+/// A read-only filesystem backed by a list of paths, such as the output of
+/// `git ls-files` or `tar tf`.
+///
+/// Paths are rooted at the empty path, so walk it with `Path::new("")`.
+/// Every ancestor of a listed path is a directory, as is any path listed with
+/// a trailing `/`; everything else is a file. Leading `/` and `.` components
+/// are ignored. Archive backends use [`PathListFileSystem::insert`] to add
+/// entries with their own kind and metadata.
+#[derive(Clone, Debug)]
+pub struct PathListFileSystem {
+    /// Children of each directory, keyed by name
+    dirs: HashMap<PathBuf, BTreeMap<String, FsEntry>>,
+}
+
+impl Default for PathListFileSystem {
+    fn default() -> Self {
+        PathListFileSystem {
+            dirs: HashMap::from([(PathBuf::new(), BTreeMap::new())]),
+        }
+    }
+}
+
+impl PathListFileSystem {
+    pub fn from_paths<I, P>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        let mut fs = Self::default();
+        for path in paths {
+            let path = path.as_ref();
+            let kind = if path.ends_with('/') {
+                EntryKind::Directory
+            } else {
+                EntryKind::File
+            };
+            fs.insert(path, kind, None);
+        }
+        fs
+    }
+
+    /// Parse a listing separated by NUL bytes if it contains any (as from
+    /// `find -print0` or `git ls-files -z`), otherwise by newlines.
+    pub fn parse(listing: &str) -> Self {
+        let separator = if listing.contains('\0') { '\0' } else { '\n' };
+        Self::from_paths(
+            listing
+                .split(separator)
+                .map(|line| line.strip_suffix('\r').unwrap_or(line)),
+        )
+    }
+
+    /// Add an entry, creating any missing ancestor directories. A later entry
+    /// for the same path replaces the earlier one, except that a directory
+    /// keeps its children.
+    pub fn insert(&mut self, path: &str, kind: EntryKind, metadata: Option<EntryMetadata>) {
+        let names: Vec<&str> = Path::new(path)
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+        let Some((last, ancestors)) = names.split_last() else {
+            return;
+        };
+
+        let mut parent = PathBuf::new();
+        for name in ancestors {
+            let path = parent.join(name);
+            let siblings = self.dirs.get_mut(&parent).expect("parent directory");
+            let entry = siblings
+                .entry((*name).to_owned())
+                .or_insert_with(|| dir_entry(&path, name));
+            // A path listed as a file may turn out to have children
+            if entry.kind != EntryKind::Directory {
+                *entry = dir_entry(&path, name);
+            }
+            self.dirs.entry(path.clone()).or_default();
+            parent = path;
+        }
+
+        let path = parent.join(last);
+        let keeps_children = kind != EntryKind::Directory
+            && self
+                .dirs
+                .get(&path)
+                .is_some_and(|children| !children.is_empty());
+        if keeps_children {
+            return;
+        }
+
+        let siblings = self.dirs.get_mut(&parent).expect("parent directory");
+        siblings.insert(
+            (*last).to_owned(),
+            FsEntry {
+                path: path.clone(),
+                name: (*last).to_owned(),
+                kind,
+                metadata,
+            },
+        );
+        if kind == EntryKind::Directory {
+            self.dirs.entry(path).or_default();
+        } else {
+            self.dirs.remove(&path);
+        }
+    }
+
+    /// Insert every entry of `other` below `prefix`, which becomes a
+    /// directory with the given metadata. Used to mount nested archives.
+    #[cfg(feature = "archives")]
+    pub(crate) fn graft(
+        &mut self,
+        prefix: &str,
+        other: &PathListFileSystem,
+        metadata: Option<EntryMetadata>,
+    ) {
+        self.insert(prefix, EntryKind::Directory, metadata);
+        for entry in other.dirs.values().flat_map(BTreeMap::values) {
+            let path = Path::new(prefix).join(&entry.path);
+            self.insert(&path.to_string_lossy(), entry.kind, entry.metadata.clone());
+        }
+    }
+
+    fn list(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        match self.dirs.get(dir) {
+            Some(children) => Ok(children.values().cloned().collect()),
+            None => Err(Error::io(
+                dir,
+                &io::Error::new(io::ErrorKind::NotFound, "not in the path list"),
+            )),
+        }
+    }
+}
+
+fn dir_entry(path: &Path, name: &str) -> FsEntry {
+    FsEntry {
+        path: path.to_path_buf(),
+        name: name.to_owned(),
+        kind: EntryKind::Directory,
+        metadata: None,
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl FileSystem for PathListFileSystem {
+    async fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        self.list(dir)
+    }
+}
+
+impl BlockingFileSystem for PathListFileSystem {
+    fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        self.list(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::render::write_children;
+    use crate::core::walk::{WalkOptions, walk_dir_blocking};
+
+    fn render(fs: &PathListFileSystem) -> String {
+        let tree = walk_dir_blocking(fs, Path::new(""), &WalkOptions::default()).unwrap();
+        let mut out = Vec::new();
+        write_children(&mut out, &tree.children).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn builds_directories_from_path_prefixes() {
+        let fs = PathListFileSystem::parse(concat!(
+            "src/main.rs\n",
+            "./src/core/walk.rs\n",
+            "docs/\n",
+            "\n",
+            "Cargo.toml\r\n",
+            "/abs/file\n",
+        ));
+
+        assert_eq!(
+            render(&fs),
+            concat!(
+                "|-- Cargo.toml\n",
+                "|-- abs/\n",
+                "|   `-- file\n",
+                "|-- docs/\n",
+                "`-- src/\n",
+                "    |-- core/\n",
+                "    |   `-- walk.rs\n",
+                "    `-- main.rs\n",
+            )
+        );
+    }
+
+    #[test]
+    fn splits_on_nul_when_present() {
+        let fs = PathListFileSystem::parse("with\nnewline\0plain\0");
+        let names: Vec<String> = BlockingFileSystem::read_dir(&fs, Path::new(""))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["plain", "with\nnewline"]);
+    }
+
+    #[test]
+    fn file_listed_before_its_children_becomes_a_directory() {
+        let fs = PathListFileSystem::from_paths(["pkg", "pkg/lib.rs"]);
+        assert_eq!(render(&fs), "`-- pkg/\n    `-- lib.rs\n");
+    }
+}
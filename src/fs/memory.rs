@@ -0,0 +1,426 @@
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::models::{EntryKind, EntryMetadata, FsEntry};
+use crate::{Error, Result};
+
+use super::BlockingFileSystem;
+#[cfg(feature = "async")]
+use super::FileSystem;
+
+/// An in-memory filesystem for deterministic tests of tree-based code.
+///
+/// Directories are created implicitly for every ancestor of an added entry.
+/// Reading a directory that does not exist fails with `NotFound`, and reading
+/// a non-directory fails with `NotADirectory`. Clones share the same state.
+#[derive(Clone, Default)]
+pub struct MemoryFileSystem {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: BTreeMap<PathBuf, FsEntry>,
+    /// Children of each directory, keyed by name
+    dirs: BTreeMap<PathBuf, BTreeMap<String, PathBuf>>,
+    errors: BTreeMap<PathBuf, Error>,
+    calls: Vec<PathBuf>,
+}
+
+/// A node of a nested filesystem literal, see [`MemoryFileSystem::from_nodes`].
+#[derive(Clone, Debug)]
+pub struct MemoryNode {
+    name: String,
+    kind: EntryKind,
+    metadata: Option<EntryMetadata>,
+    error: Option<(io::ErrorKind, String)>,
+    children: Vec<MemoryNode>,
+}
+
+impl MemoryNode {
+    pub fn dir(name: impl Into<String>, children: Vec<MemoryNode>) -> Self {
+        MemoryNode {
+            children,
+            ..Self::entry(name, EntryKind::Directory)
+        }
+    }
+
+    pub fn file(name: impl Into<String>) -> Self {
+        Self::entry(name, EntryKind::File)
+    }
+
+    pub fn symlink(name: impl Into<String>, target: impl Into<PathBuf>) -> Self {
+        let mut node = Self::entry(name, EntryKind::Symlink);
+        node.metadata = Some(EntryMetadata {
+            link_target: Some(target.into()),
+            ..EntryMetadata::default()
+        });
+        node
+    }
+
+    /// An entry of any kind, e.g. a FIFO or socket.
+    pub fn entry(name: impl Into<String>, kind: EntryKind) -> Self {
+        MemoryNode {
+            name: name.into(),
+            kind,
+            metadata: None,
+            error: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.metadata
+            .get_or_insert_with(EntryMetadata::default)
+            .size = size;
+        self
+    }
+
+    pub fn with_modified(mut self, modified: SystemTime) -> Self {
+        self.metadata
+            .get_or_insert_with(EntryMetadata::default)
+            .modified = Some(modified);
+        self
+    }
+
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.metadata
+            .get_or_insert_with(EntryMetadata::default)
+            .mode = Some(mode);
+        self
+    }
+
+    /// Make reading this directory fail.
+    pub fn with_error(mut self, kind: io::ErrorKind, message: impl Into<String>) -> Self {
+        self.error = Some((kind, message.into()));
+        self
+    }
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a filesystem from paths relative to `root`. A trailing `/` marks
+    /// a directory; everything else is a file.
+    pub fn from_paths<I, P>(root: impl AsRef<Path>, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        let fs = Self::new();
+        fs.add_dir(root.as_ref());
+        for path in paths {
+            let path = path.as_ref();
+            let full = root.as_ref().join(path.trim_end_matches('/'));
+            if path.ends_with('/') {
+                fs.add_dir(full);
+            } else {
+                fs.add_file(full);
+            }
+        }
+        fs
+    }
+
+    /// Build a filesystem from a nested literal rooted at `root`.
+    pub fn from_nodes(root: impl AsRef<Path>, nodes: Vec<MemoryNode>) -> Self {
+        let fs = Self::new();
+        fs.add_dir(root.as_ref());
+        for node in nodes {
+            fs.add_node(root.as_ref(), node);
+        }
+        fs
+    }
+
+    fn add_node(&self, parent: &Path, node: MemoryNode) {
+        let path = parent.join(&node.name);
+        self.add_entry(&path, node.kind, node.metadata);
+        if let Some((kind, message)) = node.error {
+            self.set_error(&path, kind, message);
+        }
+        for child in node.children {
+            self.add_node(&path, child);
+        }
+    }
+
+    pub fn add_dir(&self, path: impl AsRef<Path>) {
+        self.add_entry(path, EntryKind::Directory, None);
+    }
+
+    pub fn add_file(&self, path: impl AsRef<Path>) {
+        self.add_entry(path, EntryKind::File, None);
+    }
+
+    pub fn add_symlink(&self, path: impl AsRef<Path>, target: impl Into<PathBuf>) {
+        let metadata = EntryMetadata {
+            link_target: Some(target.into()),
+            ..EntryMetadata::default()
+        };
+        self.add_entry(path, EntryKind::Symlink, Some(metadata));
+    }
+
+    /// Add or replace an entry, creating any missing ancestor directories.
+    pub fn add_entry(
+        &self,
+        path: impl AsRef<Path>,
+        kind: EntryKind,
+        metadata: Option<EntryMetadata>,
+    ) {
+        let mut inner = self.inner.lock().expect("memory fs lock");
+        inner.insert(path.as_ref(), kind, metadata);
+    }
+
+    /// Make reading `dir` fail with the given error.
+    pub fn set_error(
+        &self,
+        dir: impl AsRef<Path>,
+        kind: io::ErrorKind,
+        message: impl Into<String>,
+    ) {
+        let dir = dir.as_ref();
+        let mut inner = self.inner.lock().expect("memory fs lock");
+        inner.errors.insert(
+            dir.to_path_buf(),
+            Error::Io {
+                path: dir.to_path_buf(),
+                kind,
+                message: message.into(),
+            },
+        );
+    }
+
+    /// Directories read so far, in order.
+    pub fn calls(&self) -> Vec<PathBuf> {
+        let inner = self.inner.lock().expect("memory fs lock");
+        inner.calls.clone()
+    }
+
+    pub fn clear_calls(&self) {
+        let mut inner = self.inner.lock().expect("memory fs lock");
+        inner.calls.clear();
+    }
+
+    fn respond(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        let mut inner = self.inner.lock().expect("memory fs lock");
+        inner.calls.push(dir.to_path_buf());
+
+        if let Some(error) = inner.errors.get(dir) {
+            return Err(error.clone());
+        }
+        match inner.dirs.get(dir) {
+            Some(children) => Ok(children
+                .values()
+                .map(|path| inner.entries[path].clone())
+                .collect()),
+            None if inner.entries.contains_key(dir) => Err(Error::io(
+                dir,
+                &io::Error::new(io::ErrorKind::NotADirectory, "Not a directory"),
+            )),
+            None => Err(Error::io(
+                dir,
+                &io::Error::new(io::ErrorKind::NotFound, "No such file or directory"),
+            )),
+        }
+    }
+}
+
+impl Inner {
+    fn insert(&mut self, path: &Path, kind: EntryKind, metadata: Option<EntryMetadata>) {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+        let entry = FsEntry {
+            path: path.to_path_buf(),
+            name: name.clone(),
+            kind,
+            metadata,
+        };
+
+        // An entry re-added as a directory keeps its children
+        if kind == EntryKind::Directory {
+            self.dirs.entry(path.to_path_buf()).or_default();
+        } else {
+            self.dirs.remove(path);
+        }
+        self.entries.insert(path.to_path_buf(), entry);
+
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            if self.entries.get(parent).map(|entry| entry.kind) != Some(EntryKind::Directory) {
+                self.insert(parent, EntryKind::Directory, None);
+            }
+            self.dirs
+                .get_mut(parent)
+                .expect("parent directory")
+                .insert(name, path.to_path_buf());
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl FileSystem for MemoryFileSystem {
+    async fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        self.respond(dir)
+    }
+}
+
+impl BlockingFileSystem for MemoryFileSystem {
+    fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        self.respond(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::render::write_children;
+    use crate::core::walk::{WalkOptions, walk_dir_blocking};
+    use std::time::Duration;
+
+    fn render(fs: &MemoryFileSystem, root: &str, options: &WalkOptions) -> String {
+        let tree = walk_dir_blocking(fs, Path::new(root), options).unwrap();
+        let mut out = Vec::new();
+        write_children(&mut out, &tree.children).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn builds_from_paths_with_implicit_directories() {
+        let fs = MemoryFileSystem::from_paths(
+            "/repo",
+            ["src/main.rs", "src/util/mod.rs", "docs/", "Cargo.toml"],
+        );
+
+        assert_eq!(
+            render(&fs, "/repo", &WalkOptions::default()),
+            concat!(
+                "|-- Cargo.toml\n",
+                "|-- docs/\n",
+                "`-- src/\n",
+                "    |-- main.rs\n",
+                "    `-- util/\n",
+                "        `-- mod.rs\n",
+            )
+        );
+    }
+
+    #[test]
+    fn builds_from_nested_nodes_with_metadata_and_errors() {
+        let fs = MemoryFileSystem::from_nodes(
+            "/root",
+            vec![
+                MemoryNode::dir(
+                    "data",
+                    vec![
+                        MemoryNode::file("big.bin").with_size(4096),
+                        MemoryNode::file("small.txt").with_size(10),
+                    ],
+                ),
+                MemoryNode::dir("locked", vec![])
+                    .with_error(io::ErrorKind::PermissionDenied, "Permission denied"),
+                MemoryNode::symlink("latest", "data/big.bin"),
+                MemoryNode::entry("events", EntryKind::Fifo)
+                    .with_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(60)),
+            ],
+        );
+
+        assert_eq!(
+            render(&fs, "/root", &WalkOptions::default()),
+            concat!(
+                "|-- data/\n",
+                "|   |-- big.bin\n",
+                "|   `-- small.txt\n",
+                "|-- events|\n",
+                "|-- latest\n",
+                "`-- locked/ [error: Permission denied]\n",
+            )
+        );
+
+        let options = WalkOptions {
+            min_size: Some(1024),
+            ..WalkOptions::default()
+        };
+        assert_eq!(
+            render(&fs, "/root", &options),
+            "|-- data/\n|   `-- big.bin\n`-- locked/ [error: Permission denied]\n"
+        );
+
+        let entries = BlockingFileSystem::read_dir(&fs, Path::new("/root")).unwrap();
+        let events = entries.iter().find(|entry| entry.name == "events").unwrap();
+        assert_eq!(events.kind, EntryKind::Fifo);
+        assert!(events.metadata.as_ref().unwrap().modified.is_some());
+    }
+
+    #[test]
+    fn symlinks_carry_their_target_and_mode() {
+        let fs = MemoryFileSystem::from_nodes(
+            "/root",
+            vec![
+                MemoryNode::symlink("latest", "releases/v2").with_mode(0o777),
+                MemoryNode::file("run.sh").with_mode(0o750),
+            ],
+        );
+        fs.add_symlink("/root/stale", "../gone");
+
+        let entries = BlockingFileSystem::read_dir(&fs, Path::new("/root")).unwrap();
+        let metadata = |name: &str| {
+            entries
+                .iter()
+                .find(|entry| entry.name == name)
+                .and_then(|entry| entry.metadata.clone())
+                .unwrap()
+        };
+        assert_eq!(
+            metadata("latest"),
+            EntryMetadata {
+                mode: Some(0o777),
+                link_target: Some(PathBuf::from("releases/v2")),
+                ..EntryMetadata::default()
+            }
+        );
+        assert_eq!(metadata("run.sh").mode, Some(0o750));
+        assert_eq!(metadata("run.sh").link_target, None);
+        assert_eq!(
+            metadata("stale").link_target,
+            Some(PathBuf::from("../gone"))
+        );
+    }
+
+    #[test]
+    fn missing_and_non_directory_paths_fail_with_kind() {
+        let fs = MemoryFileSystem::from_paths("/root", ["file"]);
+
+        let missing = BlockingFileSystem::read_dir(&fs, Path::new("/nope")).unwrap_err();
+        assert_eq!(missing.io_kind(), Some(io::ErrorKind::NotFound));
+
+        let file = BlockingFileSystem::read_dir(&fs, Path::new("/root/file")).unwrap_err();
+        assert_eq!(file.io_kind(), Some(io::ErrorKind::NotADirectory));
+        assert_eq!(file.path(), Some(Path::new("/root/file")));
+    }
+
+    #[test]
+    fn records_calls() {
+        let fs = MemoryFileSystem::from_paths("/root", ["a/b/c"]);
+        render(&fs, "/root", &WalkOptions::default());
+        assert_eq!(
+            fs.calls(),
+            [
+                PathBuf::from("/root"),
+                PathBuf::from("/root/a"),
+                PathBuf::from("/root/a/b"),
+            ]
+        );
+
+        fs.clear_calls();
+        assert!(fs.calls().is_empty());
+    }
+}
@@ -1,11 +1,14 @@
-use anyhow::{Result, anyhow};
+#[cfg(feature = "async")]
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use crate::models::FsEntry;
+use crate::{Error, Result};
 
+use super::BlockingFileSystem;
+#[cfg(feature = "async")]
 use super::FileSystem;
 
 #[derive(Clone, Debug)]
@@ -42,18 +45,37 @@ impl MockFileSystem {
         let inner = self.inner.lock().expect("mock fs lock");
         inner.calls.clone()
     }
-}
 
-#[async_trait]
-impl FileSystem for MockFileSystem {
-    async fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+    fn respond(&self, dir: &Path) -> Result<Vec<FsEntry>> {
         let mut inner = self.inner.lock().expect("mock fs lock");
         inner.calls.push(dir.to_path_buf());
 
         match inner.responses.get(dir) {
             Some(Response::Ok(entries)) => Ok(entries.clone()),
-            Some(Response::Err(message)) => Err(anyhow!("{message}")),
-            None => Err(anyhow!("no mock response for {}", dir.display())),
+            Some(Response::Err(message)) => Err(Error::Io {
+                path: dir.to_path_buf(),
+                kind: std::io::ErrorKind::Other,
+                message: message.clone(),
+            }),
+            None => Err(Error::Io {
+                path: dir.to_path_buf(),
+                kind: std::io::ErrorKind::NotFound,
+                message: format!("no mock response for {}", dir.display()),
+            }),
         }
     }
 }
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl FileSystem for MockFileSystem {
+    async fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        self.respond(dir)
+    }
+}
+
+impl BlockingFileSystem for MockFileSystem {
+    fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        self.respond(dir)
+    }
+}
@@ -1,20 +1,49 @@
+#[cfg(feature = "archives")]
+mod archive;
+#[cfg(feature = "git")]
+mod git;
+mod list;
 mod real;
+#[cfg(feature = "archives")]
+mod tar_archive;
+#[cfg(feature = "archives")]
+mod zip_archive;
 
+#[cfg(any(test, feature = "testing"))]
+mod memory;
 #[cfg(test)]
 mod mock;
 
+#[cfg(feature = "archives")]
+pub use archive::{ArchiveFileSystem, ArchiveFormat, ArchiveOptions};
+#[cfg(feature = "git")]
+pub use git::{GitFileSystem, RepoStatus};
+pub use list::PathListFileSystem;
 pub use real::RealFileSystem;
+#[cfg(feature = "archives")]
+pub use tar_archive::TarFileSystem;
+#[cfg(feature = "archives")]
+pub use zip_archive::ZipFileSystem;
 
+#[cfg(any(test, feature = "testing"))]
+pub use memory::{MemoryFileSystem, MemoryNode};
 #[cfg(test)]
 pub use mock::MockFileSystem;
 
-use anyhow::Result;
+#[cfg(feature = "async")]
 use async_trait::async_trait;
 use std::path::Path;
 
+use crate::Result;
 use crate::models::FsEntry;
 
+#[cfg(feature = "async")]
 #[async_trait]
 pub trait FileSystem: Send + Sync {
     async fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>>;
 }
+
+/// Synchronous counterpart of `FileSystem`, for use without an async runtime.
+pub trait BlockingFileSystem {
+    fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>>;
+}
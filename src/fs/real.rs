@@ -1,43 +1,183 @@
-use anyhow::Result;
+#[cfg(feature = "async")]
 use async_trait::async_trait;
 use std::path::Path;
+#[cfg(feature = "async")]
 use tokio::task;
 
-use crate::models::{EntryKind, FsEntry};
+use crate::models::{EntryKind, EntryMetadata, FsEntry};
+use crate::{Error, Result};
 
+use super::BlockingFileSystem;
+#[cfg(feature = "async")]
 use super::FileSystem;
 
-pub struct RealFileSystem;
+/// The local filesystem.
+///
+/// Listing a directory only reads the names and types of its entries.
+/// Sizes, times, permissions and symlink targets take a call per entry, so
+/// they are only gathered when enabled with [`RealFileSystem::with_metadata`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFileSystem {
+    metadata: bool,
+}
+
+impl RealFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
+    /// Set whether listed entries carry their metadata, e.g. for the size and
+    /// time filters of [`WalkOptions`](crate::core::walk::WalkOptions).
+    pub fn with_metadata(mut self, metadata: bool) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+#[cfg(feature = "async")]
 #[async_trait]
 impl FileSystem for RealFileSystem {
     async fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        let fs = *self;
         let dir = dir.to_path_buf();
-        task::spawn_blocking(move || {
-            let mut entries = Vec::new();
-            for entry in std::fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
-                let file_type = match entry.file_type() {
-                    Ok(file_type) => file_type,
-                    Err(_) => continue,
-                };
-                let kind = if file_type.is_symlink() {
-                    EntryKind::Symlink
-                } else if file_type.is_dir() {
-                    EntryKind::Directory
-                } else if file_type.is_file() {
-                    EntryKind::File
-                } else {
-                    EntryKind::Other
-                };
-
-                entries.push(FsEntry {
-                    path: entry.path(),
-                    name: entry.file_name().to_string_lossy().into_owned(),
-                    kind,
+        let path = dir.clone();
+        task::spawn_blocking(move || BlockingFileSystem::read_dir(&fs, &dir))
+            .await
+            .unwrap_or_else(|err| Err(Error::io(path, &std::io::Error::other(err))))
+    }
+}
+
+impl BlockingFileSystem for RealFileSystem {
+    fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        let mut entries = Vec::new();
+        let read_dir = std::fs::read_dir(dir).map_err(|err| Error::io(dir, &err))?;
+        for entry in read_dir.filter_map(|e| e.ok()) {
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+            let kind = entry_kind(&file_type);
+            // DirEntry::metadata does not traverse symlinks
+            let metadata = self
+                .metadata
+                .then(|| entry.metadata().ok())
+                .flatten()
+                .map(|metadata| EntryMetadata {
+                    size: metadata.len(),
+                    modified: metadata.modified().ok(),
+                    mode: permission_bits(&metadata),
+                    link_target: (kind == EntryKind::Symlink)
+                        .then(|| std::fs::read_link(entry.path()).ok())
+                        .flatten(),
                 });
-            }
-            Ok(entries)
-        })
-        .await?
+
+            entries.push(FsEntry {
+                path: entry.path(),
+                name: entry.file_name().to_string_lossy().into_owned(),
+                kind,
+                metadata,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+fn entry_kind(file_type: &std::fs::FileType) -> EntryKind {
+    if file_type.is_symlink() {
+        EntryKind::Symlink
+    } else if file_type.is_dir() {
+        EntryKind::Directory
+    } else if file_type.is_file() {
+        EntryKind::File
+    } else {
+        special_kind(file_type)
+    }
+}
+
+#[cfg(unix)]
+fn special_kind(file_type: &std::fs::FileType) -> EntryKind {
+    use std::os::unix::fs::FileTypeExt;
+
+    if file_type.is_fifo() {
+        EntryKind::Fifo
+    } else if file_type.is_socket() {
+        EntryKind::Socket
+    } else if file_type.is_block_device() {
+        EntryKind::BlockDevice
+    } else if file_type.is_char_device() {
+        EntryKind::CharDevice
+    } else {
+        EntryKind::Other
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(_file_type: &std::fs::FileType) -> EntryKind {
+    EntryKind::Other
+}
+
+#[cfg(unix)]
+fn permission_bits(metadata: &std::fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn permission_bits(_metadata: &std::fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_dir_errors_carry_kind_and_path() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let missing = temp.path().join("missing");
+
+        let err = BlockingFileSystem::read_dir(&RealFileSystem::new(), &missing).unwrap_err();
+        assert_eq!(err.io_kind(), Some(std::io::ErrorKind::NotFound));
+        assert_eq!(err.path(), Some(missing.as_path()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_mode_and_symlink_target() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let script = temp.path().join("run.sh");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o750)).unwrap();
+        std::os::unix::fs::symlink("run.sh", temp.path().join("latest")).unwrap();
+
+        let fs = RealFileSystem::new().with_metadata(true);
+        let entries = BlockingFileSystem::read_dir(&fs, temp.path()).unwrap();
+        let metadata = |name: &str| {
+            entries
+                .iter()
+                .find(|entry| entry.name == name)
+                .and_then(|entry| entry.metadata.clone())
+                .unwrap()
+        };
+        assert_eq!(metadata("run.sh").mode, Some(0o750));
+        assert_eq!(metadata("run.sh").link_target, None);
+        assert_eq!(
+            metadata("latest").link_target,
+            Some(std::path::PathBuf::from("run.sh"))
+        );
+    }
+
+    #[test]
+    fn skips_metadata_unless_enabled() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp.path().join("file"), "data").unwrap();
+
+        let entries = BlockingFileSystem::read_dir(&RealFileSystem::new(), temp.path()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, EntryKind::File);
+        assert_eq!(entries[0].metadata, None);
     }
 }
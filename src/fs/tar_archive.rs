@@ -0,0 +1,233 @@
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::models::{EntryKind, EntryMetadata, FsEntry};
+use crate::{Error, Result};
+
+#[cfg(feature = "async")]
+use super::FileSystem;
+use super::archive::{
+    ArchiveOptions, GZIP_MAGIC, ZSTD_MAGIC, is_nested_candidate, mount_nested, read_member,
+};
+use super::{BlockingFileSystem, PathListFileSystem};
+
+/// A read-only filesystem over the contents of a tar archive.
+///
+/// The archive is read once when opened; gzip and zstd compression are
+/// detected from the data. Sizes, modes, mtimes and link targets come from
+/// the entry headers. Like [`PathListFileSystem`], the archive root is the
+/// empty path.
+#[derive(Clone, Debug)]
+pub struct TarFileSystem {
+    entries: PathListFileSystem,
+}
+
+impl TarFileSystem {
+    /// Read the archive at `path`, which may be `.tar`, `.tar.gz` or `.tar.zst`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_options(path, &ArchiveOptions::default())
+    }
+
+    pub fn open_with_options(path: impl AsRef<Path>, options: &ArchiveOptions) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| Error::io(path, &err))?;
+        Self::build(BufReader::new(file), options)
+            .map(|entries| TarFileSystem { entries })
+            .map_err(|err| Error::io(path, &err))
+    }
+
+    /// Read a possibly compressed tar stream.
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<Self> {
+        Self::build(reader, &ArchiveOptions::default()).map(|entries| TarFileSystem { entries })
+    }
+
+    pub(crate) fn build<R: BufRead>(
+        mut reader: R,
+        options: &ArchiveOptions,
+    ) -> io::Result<PathListFileSystem> {
+        let head = reader.fill_buf()?;
+        if head.starts_with(GZIP_MAGIC) {
+            read_entries(flate2::bufread::MultiGzDecoder::new(reader), options)
+        } else if head.starts_with(ZSTD_MAGIC) {
+            read_entries(zstd::Decoder::with_buffer(reader)?, options)
+        } else {
+            read_entries(reader, options)
+        }
+    }
+}
+
+fn read_entries<R: Read>(reader: R, options: &ArchiveOptions) -> io::Result<PathListFileSystem> {
+    let mut entries = PathListFileSystem::default();
+    let mut archive = tar::Archive::new(reader);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let header = entry.header();
+        let kind = match entry_kind(header.entry_type()) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let metadata = EntryMetadata {
+            size: entry.size(),
+            modified: header
+                .mtime()
+                .ok()
+                .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+            mode: header.mode().ok(),
+            link_target: entry.link_name()?.map(|target| target.into_owned()),
+        };
+        let path = entry.path()?.to_string_lossy().into_owned();
+
+        if kind == EntryKind::File
+            && is_nested_candidate(&path, metadata.size, options)
+            && let Some(data) = read_member(&mut entry, options)?
+            && mount_nested(&mut entries, &path, &data, Some(metadata.clone()), options)
+        {
+            continue;
+        }
+        entries.insert(&path, kind, Some(metadata));
+    }
+
+    Ok(entries)
+}
+
+/// Map a header type to an entry kind, skipping metadata-only records.
+/// Hard links are listed as files, with the link target in the metadata.
+fn entry_kind(entry_type: tar::EntryType) -> Option<EntryKind> {
+    use tar::EntryType;
+
+    match entry_type {
+        EntryType::Regular | EntryType::Continuous | EntryType::Link => Some(EntryKind::File),
+        EntryType::Directory => Some(EntryKind::Directory),
+        EntryType::Symlink => Some(EntryKind::Symlink),
+        EntryType::Fifo => Some(EntryKind::Fifo),
+        EntryType::Block => Some(EntryKind::BlockDevice),
+        EntryType::Char => Some(EntryKind::CharDevice),
+        EntryType::GNUSparse => Some(EntryKind::File),
+        EntryType::GNULongName
+        | EntryType::GNULongLink
+        | EntryType::XGlobalHeader
+        | EntryType::XHeader => None,
+        _ => Some(EntryKind::Other),
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl FileSystem for TarFileSystem {
+    async fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        BlockingFileSystem::read_dir(&self.entries, dir)
+    }
+}
+
+impl BlockingFileSystem for TarFileSystem {
+    fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        BlockingFileSystem::read_dir(&self.entries, dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::render::write_children;
+    use crate::core::walk::{WalkOptions, walk_dir_blocking};
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    fn sample_tar() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_mtime(1_700_000_000);
+        header.set_size(0);
+        builder
+            .append_data(&mut header, "release/", io::empty())
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_mtime(1_700_000_100);
+        header.set_size(5);
+        builder
+            .append_data(&mut header, "release/bin/tool", &b"hello"[..])
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "release/latest", "bin/tool")
+            .unwrap();
+
+        builder.into_inner().unwrap()
+    }
+
+    fn render(fs: &TarFileSystem) -> String {
+        let tree = walk_dir_blocking(fs, Path::new(""), &WalkOptions::default()).unwrap();
+        let mut out = Vec::new();
+        write_children(&mut out, &tree.children).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    const EXPECTED: &str = concat!(
+        "`-- release/\n",
+        "    |-- bin/\n",
+        "    |   `-- tool\n",
+        "    `-- latest\n",
+    );
+
+    #[test]
+    fn reads_plain_tar_with_header_metadata() {
+        let fs = TarFileSystem::from_reader(&sample_tar()[..]).unwrap();
+        assert_eq!(render(&fs), EXPECTED);
+
+        let release = BlockingFileSystem::read_dir(&fs, Path::new("release")).unwrap();
+        let latest = release.iter().find(|entry| entry.name == "latest").unwrap();
+        assert_eq!(latest.kind, EntryKind::Symlink);
+        assert_eq!(
+            latest.metadata.as_ref().unwrap().link_target,
+            Some(PathBuf::from("bin/tool"))
+        );
+
+        let bin = BlockingFileSystem::read_dir(&fs, Path::new("release/bin")).unwrap();
+        let tool = bin[0].metadata.as_ref().unwrap();
+        assert_eq!(tool.size, 5);
+        assert_eq!(tool.mode, Some(0o644));
+        assert_eq!(
+            tool.modified,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_100))
+        );
+
+        let root = BlockingFileSystem::read_dir(&fs, Path::new("")).unwrap();
+        assert_eq!(root[0].metadata.as_ref().unwrap().mode, Some(0o755));
+    }
+
+    #[test]
+    fn detects_gzip_and_zstd_compression() {
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gzip.write_all(&sample_tar()).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let fs = TarFileSystem::from_reader(&gzip[..]).unwrap();
+        assert_eq!(render(&fs), EXPECTED);
+
+        let zstd = zstd::encode_all(&sample_tar()[..], 0).unwrap();
+        let fs = TarFileSystem::from_reader(&zstd[..]).unwrap();
+        assert_eq!(render(&fs), EXPECTED);
+    }
+
+    #[test]
+    fn open_reports_the_archive_path() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("broken.tar.gz");
+        std::fs::write(&path, [0x1f, 0x8b, 0x00]).unwrap();
+
+        let err = TarFileSystem::open(&path).unwrap_err();
+        assert_eq!(err.path(), Some(path.as_path()));
+    }
+}
@@ -0,0 +1,311 @@
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::models::{EntryKind, EntryMetadata, FsEntry};
+use crate::{Error, Result};
+
+#[cfg(feature = "async")]
+use super::FileSystem;
+use super::archive::{ArchiveOptions, is_nested_candidate, mount_nested, read_member};
+use super::{BlockingFileSystem, PathListFileSystem};
+
+/// Unix file type bits, as stored in the external attributes
+const S_IFMT: u32 = 0o170_000;
+const S_IFLNK: u32 = 0o120_000;
+
+/// A read-only filesystem over the contents of a zip archive, including
+/// zip-based formats such as jar, wheel and nupkg.
+///
+/// The central directory is read once when opened. Sizes are uncompressed
+/// sizes, and modes and symlinks are recognized when the archive was written
+/// with Unix attributes. Like [`PathListFileSystem`], the archive root is the
+/// empty path.
+#[derive(Clone, Debug)]
+pub struct ZipFileSystem {
+    entries: PathListFileSystem,
+}
+
+impl ZipFileSystem {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_options(path, &ArchiveOptions::default())
+    }
+
+    pub fn open_with_options(path: impl AsRef<Path>, options: &ArchiveOptions) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| Error::io(path, &err))?;
+        Self::build(BufReader::new(file), options)
+            .map(|entries| ZipFileSystem { entries })
+            .map_err(|err| Error::io(path, &err))
+    }
+
+    pub fn from_reader<R: Read + Seek>(reader: R) -> io::Result<Self> {
+        Self::build(reader, &ArchiveOptions::default()).map(|entries| ZipFileSystem { entries })
+    }
+
+    pub(crate) fn build<R: Read + Seek>(
+        reader: R,
+        options: &ArchiveOptions,
+    ) -> io::Result<PathListFileSystem> {
+        let mut archive = zip::ZipArchive::new(reader).map_err(io::Error::other)?;
+        let mut entries = PathListFileSystem::default();
+
+        for index in 0..archive.len() {
+            let (path, kind, mut metadata) = {
+                let file = archive.by_index_raw(index).map_err(io::Error::other)?;
+                let mode = file.unix_mode();
+                let kind = if file.is_dir() {
+                    EntryKind::Directory
+                } else if mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK) {
+                    EntryKind::Symlink
+                } else {
+                    EntryKind::File
+                };
+                let metadata = EntryMetadata {
+                    size: file.size(),
+                    modified: file.last_modified().and_then(to_system_time),
+                    mode: mode.map(|mode| mode & 0o7777),
+                    link_target: None,
+                };
+                (file.name().to_owned(), kind, metadata)
+            };
+
+            // Symlink targets and nested archives live in the entry data
+            let wants_data = kind == EntryKind::Symlink
+                || (kind == EntryKind::File && is_nested_candidate(&path, metadata.size, options));
+            let data = if wants_data {
+                read_data(&mut archive, index, options)
+            } else {
+                None
+            };
+
+            match (kind, data) {
+                (EntryKind::Symlink, Some(data)) => {
+                    metadata.link_target =
+                        Some(PathBuf::from(String::from_utf8_lossy(&data).into_owned()));
+                }
+                (EntryKind::File, Some(data))
+                    if mount_nested(
+                        &mut entries,
+                        &path,
+                        &data,
+                        Some(metadata.clone()),
+                        options,
+                    ) =>
+                {
+                    continue;
+                }
+                _ => {}
+            }
+            entries.insert(&path, kind, Some(metadata));
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Read an entry's data; unreadable (e.g. encrypted) and oversized entries
+/// yield `None`.
+fn read_data<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    index: usize,
+    options: &ArchiveOptions,
+) -> Option<Vec<u8>> {
+    let file = archive.by_index(index).ok()?;
+    read_member(file, options).ok().flatten()
+}
+
+/// Convert a zip (MS-DOS) timestamp, which has no time zone, as if it were UTC.
+fn to_system_time(time: zip::DateTime) -> Option<SystemTime> {
+    let days = days_from_civil(
+        i64::from(time.year()),
+        i64::from(time.month()),
+        i64::from(time.day()),
+    );
+    let secs = days * 86_400
+        + i64::from(time.hour()) * 3_600
+        + i64::from(time.minute()) * 60
+        + i64::from(time.second());
+    u64::try_from(secs)
+        .ok()
+        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl FileSystem for ZipFileSystem {
+    async fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        BlockingFileSystem::read_dir(&self.entries, dir)
+    }
+}
+
+impl BlockingFileSystem for ZipFileSystem {
+    fn read_dir(&self, dir: &Path) -> Result<Vec<FsEntry>> {
+        BlockingFileSystem::read_dir(&self.entries, dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::render::write_children;
+    use crate::core::walk::{WalkOptions, walk_dir_blocking};
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+
+    fn zip_bytes(build: impl FnOnce(&mut zip::ZipWriter<Cursor<Vec<u8>>>)) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        build(&mut writer);
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn render<F: BlockingFileSystem>(fs: &F) -> String {
+        let tree = walk_dir_blocking(fs, Path::new(""), &WalkOptions::default()).unwrap();
+        let mut out = Vec::new();
+        write_children(&mut out, &tree.children).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn wheel() -> Vec<u8> {
+        zip_bytes(|writer| {
+            let options = SimpleFileOptions::default()
+                .unix_permissions(0o644)
+                .last_modified_time(
+                    zip::DateTime::from_date_and_time(2024, 3, 1, 12, 0, 0).unwrap(),
+                );
+            writer.start_file("pkg/__init__.py", options).unwrap();
+            writer.write_all(b"").unwrap();
+            writer.start_file("pkg/core.py", options).unwrap();
+            writer.write_all(b"print('hi')\n").unwrap();
+            writer
+                .add_directory("pkg-1.0.dist-info/", SimpleFileOptions::default())
+                .unwrap();
+            writer
+                .add_symlink("current", "pkg", SimpleFileOptions::default())
+                .unwrap();
+        })
+    }
+
+    #[test]
+    fn reads_entries_with_metadata() {
+        let fs = ZipFileSystem::from_reader(Cursor::new(wheel())).unwrap();
+        assert_eq!(
+            render(&fs),
+            concat!(
+                "|-- current\n",
+                "|-- pkg-1.0.dist-info/\n",
+                "`-- pkg/\n",
+                "    |-- __init__.py\n",
+                "    `-- core.py\n",
+            )
+        );
+
+        let root = BlockingFileSystem::read_dir(&fs, Path::new("")).unwrap();
+        let current = root.iter().find(|entry| entry.name == "current").unwrap();
+        assert_eq!(current.kind, EntryKind::Symlink);
+        assert_eq!(
+            current.metadata.as_ref().unwrap().link_target,
+            Some(PathBuf::from("pkg"))
+        );
+
+        let pkg = BlockingFileSystem::read_dir(&fs, Path::new("pkg")).unwrap();
+        let core = pkg.iter().find(|entry| entry.name == "core.py").unwrap();
+        let metadata = core.metadata.as_ref().unwrap();
+        assert_eq!(metadata.size, 12);
+        assert_eq!(metadata.mode, Some(0o644));
+        // 2024-03-01T12:00:00Z
+        assert_eq!(
+            metadata.modified,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_294_400))
+        );
+    }
+
+    #[test]
+    fn descends_into_nested_archives_only_when_asked() {
+        let inner = wheel();
+        let outer = zip_bytes(|writer| {
+            writer
+                .start_file("lib/dep.whl", SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(&inner).unwrap();
+        });
+
+        let flat = ZipFileSystem::from_reader(Cursor::new(outer.clone())).unwrap();
+        assert_eq!(render(&flat), "`-- lib/\n    `-- dep.whl\n");
+
+        let options = ArchiveOptions {
+            nested: true,
+            ..ArchiveOptions::default()
+        };
+        let nested = ZipFileSystem {
+            entries: ZipFileSystem::build(Cursor::new(outer), &options).unwrap(),
+        };
+        assert_eq!(
+            render(&nested),
+            concat!(
+                "`-- lib/\n",
+                "    `-- dep.whl/\n",
+                "        |-- current\n",
+                "        |-- pkg-1.0.dist-info/\n",
+                "        `-- pkg/\n",
+                "            |-- __init__.py\n",
+                "            `-- core.py\n",
+            )
+        );
+    }
+
+    #[test]
+    fn stops_opening_nested_archives_at_the_limits() {
+        // Each level holds a copy of everything inside it, like a zip quine
+        let mut archive = zip_bytes(|writer| {
+            writer
+                .start_file("leaf.txt", SimpleFileOptions::default())
+                .unwrap();
+        });
+        for _ in 0..4 {
+            let inner = archive;
+            archive = zip_bytes(|writer| {
+                writer
+                    .start_file("self.zip", SimpleFileOptions::default())
+                    .unwrap();
+                writer.write_all(&inner).unwrap();
+            });
+        }
+
+        let options = ArchiveOptions {
+            nested: true,
+            max_nesting: 2,
+            ..ArchiveOptions::default()
+        };
+        let nested = ZipFileSystem {
+            entries: ZipFileSystem::build(Cursor::new(archive.clone()), &options).unwrap(),
+        };
+        assert_eq!(
+            render(&nested),
+            "`-- self.zip/\n    `-- self.zip/\n        `-- self.zip\n"
+        );
+
+        let options = ArchiveOptions {
+            nested: true,
+            max_member_size: 64,
+            ..ArchiveOptions::default()
+        };
+        let capped = ZipFileSystem {
+            entries: ZipFileSystem::build(Cursor::new(archive), &options).unwrap(),
+        };
+        assert_eq!(render(&capped), "`-- self.zip\n");
+    }
+}
@@ -1,7 +1,10 @@
 pub mod core;
+pub mod error;
 pub mod fs;
 pub mod models;
 
+pub use error::{Error, Result};
+
 use std::path::Path;
 
 pub fn root_display_name(root_path: &Path, is_current_dir: bool) -> String {
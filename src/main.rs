@@ -1,16 +1,146 @@
 use clap::Parser;
-use std::io::Write;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
+use rtree::core::diff::{ChangeCheck, TreeDiff, diff_trees, is_modified};
+use rtree::core::duplicates::mark_duplicates;
+use rtree::core::hash::{HashOptions, hash_tree};
+use rtree::core::render::RenderOptions;
+use rtree::core::serialize::TreeDocument;
+use rtree::core::walk::WalkOptions;
+use rtree::fs::{
+    ArchiveFileSystem, ArchiveOptions, FileSystem, GitFileSystem, RealFileSystem, RepoStatus,
+};
+use rtree::models::{DirTree, EntryKind, HashAlgorithm};
+
 mod cli;
 
 #[tokio::main]
 async fn main() -> ExitCode {
     let args = cli::Cli::parse();
+    if let Some(cli::Command::Apply(apply)) = args.command {
+        return apply_layout(&apply);
+    }
+
+    let compact_dirs = args.compact_dirs;
+    let nested_archives = args.nested_archives;
+    let git_status = args.git_status;
+    let hash = args.hash.map(|algorithm| HashOptions {
+        algorithm,
+        directories: args.hash_dirs,
+        threads: None,
+    });
+    let duplicates = args.duplicates.then(|| HashOptions {
+        algorithm: args.hash.unwrap_or(HashAlgorithm::Blake3),
+        ..HashOptions::default()
+    });
+    let options = WalkOptions {
+        max_depth: args.level,
+        ignore_pattern: args.ignore_pattern,
+        // show_hidden is true by default; -a flag is a no-op (keeps it true)
+        show_hidden: true,
+        dirs_only: args.dirs_only,
+        dirs_first: args.dirs_first,
+        entry_types: (!args.types.is_empty()).then(|| args.types.into_iter().collect()),
+        prune: args.prune,
+        min_size: args.min_size,
+        max_size: args.max_size,
+        newer_than: args.newer,
+        older_than: args.older,
+        file_limit: args.file_limit,
+    };
+    let render_options = RenderOptions {
+        max_children: args.max_children,
+    };
+
+    if let Some(roots) = args.diff {
+        return print_diff(
+            &roots[0],
+            &roots[1],
+            &options,
+            &render_options,
+            &args.changed_by,
+        )
+        .await;
+    }
+
+    if args.from_file {
+        // The listing is read from PATH, or from stdin when PATH is absent or `-`
+        let source = args.path.filter(|path| path != Path::new("-"));
+        let listing = match read_listing(source.as_deref()) {
+            Ok(listing) => listing,
+            Err(err) => {
+                let name = source.as_deref().unwrap_or(Path::new("stdin"));
+                eprintln!("rtree: {}: {}", name.display(), err);
+                return ExitCode::from(1);
+            }
+        };
+        let root_name = source.map_or_else(
+            || ".".to_owned(),
+            |path| rtree::root_display_name(&path, false),
+        );
+        let fs = rtree::fs::PathListFileSystem::parse(&listing);
+        let transforms = Transforms {
+            compact_dirs,
+            ..Transforms::default()
+        };
+        return print_tree(
+            &fs,
+            Path::new(""),
+            &root_name,
+            &options,
+            &render_options,
+            &transforms,
+        )
+        .await;
+    }
+
     let root_path = args.path.unwrap_or_else(|| PathBuf::from("."));
     let is_current_dir = root_path == Path::new(".");
 
+    if let Some(rev) = args.git_rev {
+        let fs = match GitFileSystem::open(&root_path, &rev) {
+            Ok(fs) => fs,
+            Err(err) => {
+                eprintln!("rtree: {}: {}", root_path.display(), err);
+                return ExitCode::from(1);
+            }
+        };
+        let root_name = rtree::root_display_name(&root_path, is_current_dir);
+        let transforms = Transforms {
+            compact_dirs,
+            ..Transforms::default()
+        };
+        return print_tree(
+            &fs,
+            Path::new(""),
+            &root_name,
+            &options,
+            &render_options,
+            &transforms,
+        )
+        .await;
+    }
+
+    if let Some(snapshot) = args.snapshot {
+        let root_name = rtree::root_display_name(&root_path, is_current_dir);
+        return save_snapshot(&root_path, &root_name, &snapshot, &options, hash.as_ref()).await;
+    }
+
+    if let Some(snapshot) = args.compare {
+        let root_name = rtree::root_display_name(&root_path, is_current_dir);
+        return compare_snapshot(
+            &root_path,
+            &root_name,
+            &snapshot,
+            options.dirs_first,
+            &render_options,
+            hash.as_ref(),
+        )
+        .await;
+    }
+
     let metadata = match std::fs::symlink_metadata(&root_path) {
         Ok(metadata) => metadata,
         Err(err) => {
@@ -19,6 +149,55 @@ async fn main() -> ExitCode {
         }
     };
 
+    // Only regular files (or links to them) are sniffed; reading a FIFO would block
+    let is_file = std::fs::metadata(&root_path).is_ok_and(|metadata| metadata.is_file());
+    if !metadata.is_dir() && is_file {
+        let archive_options = ArchiveOptions {
+            nested: nested_archives,
+            ..ArchiveOptions::default()
+        };
+        match ArchiveFileSystem::open(&root_path, &archive_options) {
+            // Archive members are not on disk, so there is nothing to hash
+            // and no working copy to compare against
+            Ok(Some(_)) if hash.is_some() || duplicates.is_some() || git_status => {
+                let flag = if hash.is_some() {
+                    "--hash"
+                } else if duplicates.is_some() {
+                    "--duplicates"
+                } else {
+                    "--git-status"
+                };
+                eprintln!(
+                    "rtree: {}: {flag} is not supported for archives",
+                    root_path.display()
+                );
+                return ExitCode::from(1);
+            }
+            Ok(Some(fs)) => {
+                let root_name = rtree::root_display_name(&root_path, false);
+                let transforms = Transforms {
+                    compact_dirs,
+                    ..Transforms::default()
+                };
+                return print_tree(
+                    &fs,
+                    Path::new(""),
+                    &root_name,
+                    &options,
+                    &render_options,
+                    &transforms,
+                )
+                .await;
+            }
+            // Not an archive: print just the file name, like tree(1)
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("rtree: {}: {}", root_path.display(), err);
+                return ExitCode::from(1);
+            }
+        }
+    }
+
     if !metadata.is_dir() {
         let file_name = root_path
             .file_name()
@@ -28,37 +207,328 @@ async fn main() -> ExitCode {
         return ExitCode::SUCCESS;
     }
 
-    let fs = rtree::fs::RealFileSystem;
-    let options = rtree::core::walk::WalkOptions {
-        max_depth: args.level,
-        ignore_pattern: args.ignore_pattern,
-        // show_hidden is true by default; -a flag is a no-op (keeps it true)
-        show_hidden: true,
-        dirs_only: args.dirs_only,
-        dirs_first: args.dirs_first,
+    let status = if git_status {
+        match RepoStatus::open(&root_path) {
+            Ok(status) => Some(status),
+            Err(err) => {
+                eprintln!("rtree: {}: {}", root_path.display(), err);
+                return ExitCode::from(1);
+            }
+        }
+    } else {
+        None
     };
-    let tree = match rtree::core::walk::walk_dir(&fs, &root_path, &options).await {
+
+    let root_name = rtree::root_display_name(&root_path, is_current_dir);
+    let transforms = Transforms {
+        compact_dirs,
+        status: status.as_ref(),
+        hash: hash.as_ref(),
+        duplicates: duplicates.as_ref(),
+    };
+    let fs = RealFileSystem::new().with_metadata(options.has_metadata_filters());
+    print_tree(
+        &fs,
+        &root_path,
+        &root_name,
+        &options,
+        &render_options,
+        &transforms,
+    )
+    .await
+}
+
+/// Walk both roots and write their merged tree, followed by a summary.
+async fn print_diff(
+    old_root: &Path,
+    new_root: &Path,
+    options: &WalkOptions,
+    render_options: &RenderOptions,
+    checks: &[ChangeCheck],
+) -> ExitCode {
+    let mut trees = Vec::with_capacity(2);
+    for root in [old_root, new_root] {
+        match walk_root(root, options).await {
+            Ok(tree) => trees.push(tree),
+            Err(err) => {
+                eprintln!("rtree: {}: {}", root.display(), err);
+                return ExitCode::from(1);
+            }
+        }
+    }
+
+    let diff = diff_trees(&trees[0], &trees[1], options.dirs_first, |path, _| {
+        is_modified(&old_root.join(path), &new_root.join(path), checks)
+    });
+
+    let root_name = |root: &Path| rtree::root_display_name(root, root == Path::new("."));
+    if let Err(err) = write_diff(
+        &root_name(old_root),
+        &root_name(new_root),
+        &diff,
+        render_options,
+    ) {
+        eprintln!("rtree: stdout: {err}");
+        return ExitCode::from(1);
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Walk `root`, hashing it when `hash` is given, and save it as a JSON
+/// snapshot at `snapshot`.
+async fn save_snapshot(
+    root: &Path,
+    root_name: &str,
+    snapshot: &Path,
+    options: &WalkOptions,
+    hash: Option<&HashOptions>,
+) -> ExitCode {
+    let mut tree = match walk_root(root, options).await {
         Ok(tree) => tree,
         Err(err) => {
-            eprintln!("rtree: {err}");
+            eprintln!("rtree: {}: {}", root.display(), err);
             return ExitCode::from(1);
         }
     };
+    if let Some(hash) = hash {
+        hash_tree(&mut tree, root, hash);
+    }
+
+    let document = TreeDocument::new(root_name, tree).with_filters(options);
+    if let Err(err) = document.save(snapshot) {
+        eprintln!("rtree: {}: {}", snapshot.display(), err);
+        return ExitCode::from(1);
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Diff `root`, walked with the snapshot's options, against a saved
+/// snapshot. Like diff(1), exits with 0 when nothing changed, 1 when
+/// something did and 2 on errors.
+async fn compare_snapshot(
+    root: &Path,
+    root_name: &str,
+    snapshot: &Path,
+    dirs_first: bool,
+    render_options: &RenderOptions,
+    hash: Option<&HashOptions>,
+) -> ExitCode {
+    let document = match TreeDocument::load(snapshot) {
+        Ok(document) => document,
+        Err(err) => {
+            eprintln!("rtree: {}: {}", snapshot.display(), err);
+            return ExitCode::from(2);
+        }
+    };
+    let options = WalkOptions {
+        dirs_first,
+        ..WalkOptions::from(&document.filters)
+    };
+    let mut tree = match walk_root(root, &options).await {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("rtree: {}: {}", root.display(), err);
+            return ExitCode::from(2);
+        }
+    };
+    if let Some(hash) = hash {
+        hash_tree(&mut tree, root, hash);
+    }
+
+    let diff = document.diff(&tree, dirs_first);
+    if let Err(err) = write_diff(&document.root, root_name, &diff, render_options) {
+        eprintln!("rtree: stdout: {err}");
+        return ExitCode::from(2);
+    }
+
+    if diff.summary.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
+
+/// Walk a directory into memory, failing if the root itself cannot be read.
+async fn walk_root(root: &Path, options: &WalkOptions) -> Result<DirTree, String> {
+    match std::fs::metadata(root) {
+        Ok(metadata) if metadata.is_dir() => {
+            let fs = RealFileSystem::new().with_metadata(options.has_metadata_filters());
+            rtree::core::walk::walk_dir(&fs, root, options)
+                .await
+                .and_then(|tree| match tree.error {
+                    Some(err) => Err(err),
+                    None => Ok(tree),
+                })
+                .map_err(|err| err.to_string())
+        }
+        Ok(_) => Err("Not a directory".to_owned()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Write a diff as `OLD -> NEW`, the merged tree and the summary.
+fn write_diff(
+    old_name: &str,
+    new_name: &str,
+    diff: &TreeDiff,
+    render_options: &RenderOptions,
+) -> std::io::Result<()> {
+    use std::io::Write;
 
     let mut stdout = std::io::stdout().lock();
-    if let Err(err) = (|| -> std::io::Result<()> {
-        write!(
+    writeln!(stdout, "{old_name} -> {new_name}")?;
+    rtree::core::render::write_diff_children(&mut stdout, &diff.children, render_options)?;
+    rtree::core::render::write_diff_summary(&mut stdout, &diff.summary)
+}
+
+/// Create the entries of a tree-formatted layout under the target directory,
+/// leaving existing ones alone, and print one line per entry.
+fn apply_layout(args: &cli::ApplyArgs) -> ExitCode {
+    let source = Some(args.layout.as_path()).filter(|path| *path != Path::new("-"));
+    let source_name = source.unwrap_or(Path::new("stdin"));
+    let tree = match read_listing(source) {
+        Ok(text) => rtree::core::parse::parse_tree(&text).map_err(|err| err.to_string()),
+        Err(err) => Err(err.to_string()),
+    };
+    let tree = match tree {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("rtree: {}: {}", source_name.display(), err);
+            return ExitCode::from(1);
+        }
+    };
+
+    let target = args.target.as_deref().unwrap_or(Path::new("."));
+    let actions = match rtree::core::parse::materialize(&tree, target, args.dry_run) {
+        Ok(actions) => actions,
+        Err(err) => {
+            eprintln!("rtree: {}: {}", err.path().unwrap_or(target).display(), err);
+            return ExitCode::from(1);
+        }
+    };
+    for action in actions {
+        let verb = if action.exists { "exists" } else { "create" };
+        let slash = if action.kind == EntryKind::Directory {
+            "/"
+        } else {
+            ""
+        };
+        println!("{verb} {}{slash}", action.path.display());
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn read_listing(path: Option<&Path>) -> std::io::Result<String> {
+    let bytes = match path {
+        Some(path) => std::fs::read(path)?,
+        None => {
+            let mut bytes = Vec::new();
+            std::io::stdin().lock().read_to_end(&mut bytes)?;
+            bytes
+        }
+    };
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Rewrites of a tree that need it built in memory before rendering.
+#[derive(Default)]
+struct Transforms<'a> {
+    compact_dirs: bool,
+    status: Option<&'a RepoStatus>,
+    /// Only meaningful on the real filesystem, where `root` is on disk
+    hash: Option<&'a HashOptions>,
+    /// Like `hash`; also adds a summary after the tree
+    duplicates: Option<&'a HashOptions>,
+}
+
+impl Transforms<'_> {
+    fn is_empty(&self) -> bool {
+        !self.compact_dirs
+            && self.status.is_none()
+            && self.hash.is_none()
+            && self.duplicates.is_none()
+    }
+}
+
+/// Walk `root` on `fs`, apply `transforms` and write the tree to stdout.
+async fn print_tree<F: FileSystem>(
+    fs: &F,
+    root: &Path,
+    root_name: &str,
+    options: &WalkOptions,
+    render_options: &RenderOptions,
+    transforms: &Transforms<'_>,
+) -> ExitCode {
+    let mut stdout = std::io::stdout().lock();
+
+    let result = if !transforms.is_empty() {
+        // Transforms rewrite the whole tree, so it must be built in memory first
+        let mut tree = match rtree::core::walk::walk_dir(fs, root, options).await {
+            Ok(tree) => tree,
+            Err(err) => {
+                eprintln!("rtree: {err}");
+                return ExitCode::from(1);
+            }
+        };
+        if let Some(status) = transforms.status {
+            status.annotate(&mut tree);
+        }
+        // Hash before compacting, while node paths still match the disk
+        if let Some(hash) = transforms.hash {
+            hash_tree(&mut tree, root, hash);
+        }
+        let duplicates = transforms
+            .duplicates
+            .map(|options| mark_duplicates(&mut tree, root, options));
+        if transforms.compact_dirs {
+            rtree::core::transform::compact_dirs(&mut tree);
+        }
+
+        (|| -> std::io::Result<()> {
+            rtree::core::render::write_root(
+                &mut stdout,
+                root_name,
+                tree.error.as_ref(),
+                tree.exceeded_filelimit,
+            )?;
+            rtree::core::render::write_children_with_options(
+                &mut stdout,
+                &tree.children,
+                render_options,
+            )?;
+            match duplicates {
+                Some(summary) => {
+                    rtree::core::render::write_duplicate_summary(&mut stdout, &summary)
+                }
+                None => Ok(()),
+            }
+        })()
+    } else {
+        let mut walker = match rtree::core::walk::walk_dir_streaming(fs, root, options).await {
+            Ok(walker) => walker,
+            Err(err) => {
+                eprintln!("rtree: {err}");
+                return ExitCode::from(1);
+            }
+        };
+
+        match rtree::core::render::write_root(
             &mut stdout,
-            "{}",
-            rtree::root_display_name(&root_path, is_current_dir)
-        )?;
-        if let Some(error) = tree.error.as_ref() {
-            write!(&mut stdout, " [error: {error}]")?;
+            root_name,
+            walker.root_error(),
+            walker.root_exceeded_filelimit(),
+        ) {
+            Ok(()) => {
+                rtree::core::render::write_walk(&mut stdout, &mut walker, render_options).await
+            }
+            Err(err) => Err(err),
         }
-        writeln!(&mut stdout)?;
-        rtree::core::render::write_children(&mut stdout, &tree.children)?;
-        Ok(())
-    })() {
+    };
+
+    if let Err(err) = result {
         eprintln!("rtree: stdout: {err}");
         return ExitCode::from(1);
     }
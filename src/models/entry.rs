@@ -1,16 +1,115 @@
 use std::path::PathBuf;
+use std::time::SystemTime;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum EntryKind {
     Directory,
     File,
     Symlink,
+    /// Named pipe (FIFO)
+    Fifo,
+    /// Unix domain socket
+    Socket,
+    /// Block device (e.g. `/dev/sda`)
+    BlockDevice,
+    /// Character device (e.g. `/dev/null`)
+    CharDevice,
+    /// Any file type the platform cannot classify further
     Other,
 }
 
+impl EntryKind {
+    /// The `ls -F` style indicator rendered after the name: `|` for FIFOs
+    /// and `=` for sockets. Directories carry their `/` in the name itself.
+    pub fn indicator(self) -> Option<char> {
+        match self {
+            EntryKind::Fifo => Some('|'),
+            EntryKind::Socket => Some('='),
+            _ => None,
+        }
+    }
+
+    /// Parse a `find -type` style letter (`f`, `d`, `l`, `p`, `s`, `b`, `c`).
+    pub fn from_type_char(c: char) -> Option<Self> {
+        match c {
+            'f' => Some(EntryKind::File),
+            'd' => Some(EntryKind::Directory),
+            'l' => Some(EntryKind::Symlink),
+            'p' => Some(EntryKind::Fifo),
+            's' => Some(EntryKind::Socket),
+            'b' => Some(EntryKind::BlockDevice),
+            'c' => Some(EntryKind::CharDevice),
+            _ => None,
+        }
+    }
+
+    /// The `find -type` style letter for this kind, if it has one.
+    pub fn type_char(self) -> Option<char> {
+        match self {
+            EntryKind::File => Some('f'),
+            EntryKind::Directory => Some('d'),
+            EntryKind::Symlink => Some('l'),
+            EntryKind::Fifo => Some('p'),
+            EntryKind::Socket => Some('s'),
+            EntryKind::BlockDevice => Some('b'),
+            EntryKind::CharDevice => Some('c'),
+            EntryKind::Other => None,
+        }
+    }
+}
+
+/// Size and timestamp information for an entry, when the filesystem provides it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryMetadata {
+    /// Size in bytes (not followed for symlinks)
+    pub size: u64,
+    /// Last modification time, if the platform reports one
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub modified: Option<SystemTime>,
+    /// Unix permission bits, where the source records them
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub mode: Option<u32>,
+    /// Target of a symlink or hard link
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub link_target: Option<PathBuf>,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FsEntry {
     pub path: PathBuf,
     pub name: String,
     pub kind: EntryKind,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub metadata: Option<EntryMetadata>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_chars_round_trip() {
+        for c in ['f', 'd', 'l', 'p', 's', 'b', 'c'] {
+            let kind = EntryKind::from_type_char(c).unwrap();
+            assert_eq!(kind.type_char(), Some(c));
+        }
+        assert_eq!(EntryKind::from_type_char('x'), None);
+        assert_eq!(EntryKind::Other.type_char(), None);
+    }
 }
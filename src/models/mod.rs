@@ -1,5 +1,5 @@
 mod entry;
 mod tree;
 
-pub use entry::{EntryKind, FsEntry};
-pub use tree::{DirTree, TreeNode};
+pub use entry::{EntryKind, EntryMetadata, FsEntry};
+pub use tree::{Digest, DirTree, GitStatus, HashAlgorithm, TreeNode};
@@ -1,15 +1,125 @@
 use super::EntryKind;
+use crate::Error;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TreeNode {
     pub name: String,
     pub kind: EntryKind,
-    pub error: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub error: Option<Error>,
+    /// Entry count of a directory that was not opened because it exceeds the file limit
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub exceeded_filelimit: Option<usize>,
+    /// Git status of the entry, or for a directory the most significant
+    /// status of its contents
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub status: Option<GitStatus>,
+    /// Content digest of a file or symlink, or for a directory a digest of
+    /// its listed children
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub digest: Option<Digest>,
+    /// Group id shared with the other files that have the same contents
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub duplicate: Option<usize>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
     pub children: Vec<TreeNode>,
 }
 
+/// Working-copy state of an entry, as reported by `git status`.
+///
+/// Variants are ordered from most to least significant.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum GitStatus {
+    /// Changed in the index or the working tree (including renames,
+    /// deletions and conflicts)
+    Modified,
+    /// New in the index
+    Added,
+    /// Not tracked and not ignored
+    Untracked,
+    /// Matched by an ignore rule
+    Ignored,
+}
+
+impl GitStatus {
+    /// The `git status --short` marker: `M`, `A`, `??` or `!!`.
+    pub fn marker(self) -> &'static str {
+        match self {
+            GitStatus::Modified => "M",
+            GitStatus::Added => "A",
+            GitStatus::Untracked => "??",
+            GitStatus::Ignored => "!!",
+        }
+    }
+}
+
+/// A content digest, stored in full and shown truncated.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Digest {
+    pub algorithm: HashAlgorithm,
+    /// Lowercase hexadecimal digest
+    pub hex: String,
+}
+
+impl Digest {
+    /// Number of hex digits shown in rendered trees
+    pub const SHORT_LEN: usize = 12;
+
+    /// The leading `SHORT_LEN` hex digits.
+    pub fn short(&self) -> &str {
+        &self.hex[..self.hex.len().min(Self::SHORT_LEN)]
+    }
+}
+
+/// Hash function used for content digests.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Blake3,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DirTree {
-    pub error: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub error: Option<Error>,
+    /// Entry count of a directory that was not opened because it exceeds the file limit
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub exceeded_filelimit: Option<usize>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
     pub children: Vec<TreeNode>,
 }
@@ -1,10 +1,11 @@
 use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
 use std::fs;
 use tempfile::TempDir;
 
 fn rtree_cmd() -> Command {
-    Command::cargo_bin("rtree").unwrap()
+    cargo_bin_cmd!("rtree")
 }
 
 fn create_test_structure(temp: &TempDir) {
@@ -87,6 +88,27 @@ fn baseline_symlinks_shown_as_leaf_nodes() {
     }
 }
 
+#[cfg(unix)]
+#[test]
+fn baseline_special_files_shown_and_excluded_by_dirs_only() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::create_dir(root.join("dir")).unwrap();
+    let _listener = std::os::unix::net::UnixListener::bind(root.join("app.sock")).unwrap();
+
+    let output = rtree_cmd().arg(root).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("app.sock=\n"));
+
+    let output = rtree_cmd().arg("-d").arg(root).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("dir/"));
+    assert!(!stdout.contains("app.sock"));
+}
+
 #[test]
 fn baseline_error_for_nonexistent_path() {
     let output = rtree_cmd()
@@ -519,3 +541,704 @@ fn flag_ignore_combined_glob_patterns() {
     assert!(!stdout.contains("app.log"));
     assert!(!stdout.contains("cache.tmp"));
 }
+
+#[cfg(unix)]
+#[test]
+fn flag_type_filter_symlinks_only() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::create_dir_all(root.join("links")).unwrap();
+    fs::create_dir_all(root.join("plain")).unwrap();
+    fs::write(root.join("plain/file.txt"), "content").unwrap();
+    std::os::unix::fs::symlink("../plain/file.txt", root.join("links/alias")).unwrap();
+
+    let output = rtree_cmd()
+        .arg("--type")
+        .arg("l")
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("alias"));
+    assert!(stdout.contains("plain/"));
+    assert!(!stdout.contains("file.txt"));
+
+    let output = rtree_cmd()
+        .args(["--type", "l", "--prune"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("links/"));
+    assert!(!stdout.contains("plain/"));
+}
+
+#[test]
+fn flag_type_filter_rejects_unknown_type() {
+    rtree_cmd()
+        .args(["--type", "x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid type"));
+}
+
+#[test]
+fn flag_min_size_keeps_only_large_files_and_their_parents() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::create_dir_all(root.join("artifacts")).unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(root.join("artifacts/app.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(root.join("docs/readme.txt"), "small").unwrap();
+
+    let output = rtree_cmd()
+        .args(["--min-size", "1K"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("artifacts/"));
+    assert!(stdout.contains("app.bin"));
+    assert!(!stdout.contains("docs/"));
+    assert!(!stdout.contains("readme.txt"));
+}
+
+#[test]
+fn flag_newer_accepts_duration_and_reference_file() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::write(root.join("fresh.txt"), "content").unwrap();
+
+    let output = rtree_cmd()
+        .args(["--newer", "1d"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("fresh.txt"));
+
+    let output = rtree_cmd()
+        .arg("--older")
+        .arg(root.join("fresh.txt"))
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("fresh.txt"));
+}
+
+#[test]
+fn flag_filelimit_skips_large_directories() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::create_dir_all(root.join("spool")).unwrap();
+    for i in 0..5 {
+        fs::write(root.join(format!("spool/msg{i}")), "content").unwrap();
+    }
+    fs::create_dir_all(root.join("small")).unwrap();
+    fs::write(root.join("small/keep.txt"), "content").unwrap();
+
+    let output = rtree_cmd()
+        .args(["--filelimit", "3"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("spool/ [5 entries exceeds filelimit, not opening dir]"));
+    assert!(!stdout.contains("msg0"));
+    assert!(stdout.contains("keep.txt"));
+}
+
+#[test]
+fn flag_max_children_summarizes_remaining_entries() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    for name in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+        fs::write(root.join(name), "content").unwrap();
+    }
+
+    let output = rtree_cmd()
+        .args(["--max-children", "2"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("|-- a.txt\n|-- b.txt\n`-- ... 2 more entries\n"));
+    assert!(!stdout.contains("c.txt"));
+}
+
+#[test]
+fn flag_compact_dirs_merges_single_child_chains() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::create_dir_all(root.join("src/main/java/com/acme")).unwrap();
+    fs::write(root.join("src/main/java/com/acme/App.java"), "content").unwrap();
+    fs::write(root.join("build.gradle"), "content").unwrap();
+
+    let output = rtree_cmd()
+        .arg("--compact-dirs")
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("|-- build.gradle\n`-- src/main/java/com/acme/\n    `-- App.java\n"));
+}
+
+#[test]
+fn flag_fromfile_reads_paths_from_stdin() {
+    let output = rtree_cmd()
+        .args(["--fromfile", "-L", "1"])
+        .write_stdin("src/main.rs\nsrc/lib.rs\nCargo.toml\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        ".\n|-- Cargo.toml\n`-- src/\n"
+    );
+}
+
+#[test]
+fn flag_fromfile_reads_nul_separated_listing_file() {
+    let temp = TempDir::new().unwrap();
+    let listing = temp.path().join("files.txt");
+    fs::write(&listing, "docs/guide.md\0docs/old/\0").unwrap();
+
+    let output = rtree_cmd()
+        .args(["--fromfile", "--prune"])
+        .arg(&listing)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "files.txt\n`-- docs/\n    `-- guide.md\n"
+    );
+}
+
+#[test]
+fn tar_archive_root_renders_archive_contents() {
+    let temp = TempDir::new().unwrap();
+    let archive_path = temp.path().join("release.tar.gz");
+
+    let encoder = flate2::write::GzEncoder::new(
+        fs::File::create(&archive_path).unwrap(),
+        flate2::Compression::fast(),
+    );
+    let mut builder = tar::Builder::new(encoder);
+    for (path, contents) in [("release/bin/tool", "binary"), ("release/README", "docs")] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, path, contents.as_bytes())
+            .unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let output = rtree_cmd().arg(&archive_path).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        concat!(
+            "release.tar.gz\n",
+            "`-- release/\n",
+            "    |-- README\n",
+            "    `-- bin/\n",
+            "        `-- tool\n",
+        )
+    );
+}
+
+#[test]
+fn zip_archive_root_is_detected_and_nested_archives_are_optional() {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    let zip_bytes = |files: &[(&str, &[u8])]| {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    };
+
+    let temp = TempDir::new().unwrap();
+    // No archive extension: the format is sniffed from the contents
+    let bundle = temp.path().join("bundle.bin");
+    let inner = zip_bytes(&[("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\n")]);
+    fs::write(
+        &bundle,
+        zip_bytes(&[("app/main.py", b"print()\n"), ("lib/dep.jar", &inner)]),
+    )
+    .unwrap();
+
+    let output = rtree_cmd().arg(&bundle).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        concat!(
+            "bundle.bin\n",
+            "|-- app/\n",
+            "|   `-- main.py\n",
+            "`-- lib/\n",
+            "    `-- dep.jar\n",
+        )
+    );
+
+    let output = rtree_cmd()
+        .arg("--nested-archives")
+        .arg(&bundle)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with(concat!(
+        "`-- lib/\n",
+        "    `-- dep.jar/\n",
+        "        `-- META-INF/\n",
+        "            `-- MANIFEST.MF\n",
+    )));
+}
+
+fn git(dir: &std::path::Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args([
+            "-c",
+            "user.name=rtree",
+            "-c",
+            "user.email=rtree@example.com",
+        ])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn git_rev_renders_a_revision_without_the_working_copy() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    git(root, &["init", "-q"]);
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "first"]);
+    git(root, &["tag", "v1"]);
+
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+    fs::write(root.join("untracked.txt"), "").unwrap();
+    git(root, &["add", "src/lib.rs"]);
+    git(root, &["commit", "-q", "-m", "second"]);
+
+    let output = rtree_cmd()
+        .args(["--git-rev", "v1"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let name = root.file_name().unwrap().to_string_lossy();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{name}\n`-- src/\n    `-- main.rs\n")
+    );
+
+    let output = rtree_cmd()
+        .args(["--git-rev", "HEAD"])
+        .arg(root.join("src"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "src\n|-- lib.rs\n`-- main.rs\n"
+    );
+
+    rtree_cmd()
+        .args(["--git-rev", "no-such-rev"])
+        .arg(root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("rtree: "));
+}
+
+#[test]
+fn git_status_annotates_entries_and_directories() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    git(root, &["init", "-q"]);
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+    fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "init"]);
+
+    fs::write(root.join("src/main.rs"), "fn main() { todo!() }\n").unwrap();
+    fs::write(root.join("notes.txt"), "").unwrap();
+    fs::write(root.join("debug.log"), "").unwrap();
+
+    let output = rtree_cmd()
+        .args(["--git-status", "-I", ".git"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let name = root.file_name().unwrap().to_string_lossy();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!(
+            concat!(
+                "{}\n",
+                "|-- .gitignore\n",
+                "|-- debug.log [!!]\n",
+                "|-- notes.txt [??]\n",
+                "`-- src/ [M]\n",
+                "    |-- lib.rs\n",
+                "    `-- main.rs [M]\n",
+            ),
+            name
+        )
+    );
+
+    let outside = TempDir::new().unwrap();
+    rtree_cmd()
+        .arg("--git-status")
+        .arg(outside.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn git_status_rejects_archive_roots() {
+    let temp = TempDir::new().unwrap();
+    git(temp.path(), &["init", "-q"]);
+    let archive_path = temp.path().join("release.tar");
+
+    let mut builder = tar::Builder::new(fs::File::create(&archive_path).unwrap());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(4);
+    header.set_mode(0o644);
+    builder
+        .append_data(&mut header, "release/README", "docs".as_bytes())
+        .unwrap();
+    builder.into_inner().unwrap();
+
+    rtree_cmd()
+        .arg("--git-status")
+        .arg(&archive_path)
+        .assert()
+        .code(1)
+        .stdout("")
+        .stderr(predicate::str::ends_with(
+            "--git-status is not supported for archives\n",
+        ));
+}
+
+#[test]
+fn diff_merges_two_roots_and_summarizes_changes() {
+    let temp = TempDir::new().unwrap();
+    let old = temp.path().join("release-1.0");
+    let new = temp.path().join("release-1.1");
+    for (root, app) in [(&old, "v1"), (&new, "v1.1")] {
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::write(root.join("bin/app"), app).unwrap();
+        fs::write(root.join("bin/helper"), "same").unwrap();
+    }
+    fs::create_dir(old.join("docs")).unwrap();
+    fs::write(old.join("docs/guide.md"), "").unwrap();
+    fs::write(new.join("NEWS"), "").unwrap();
+
+    let output = rtree_cmd()
+        .arg("--diff")
+        .args([&old, &new])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        concat!(
+            "release-1.0 -> release-1.1\n",
+            "|-- NEWS [+]\n",
+            "|-- bin/\n",
+            "|   |-- app\n",
+            "|   `-- helper\n",
+            "`-- docs/ [-]\n",
+            "    `-- guide.md [-]\n",
+            "\n",
+            "1 added, 2 removed, 0 modified\n",
+        )
+    );
+
+    let output = rtree_cmd()
+        .args(["--changed-by", "content", "--diff"])
+        .args([&old, &new])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("|   |-- app [~]\n|   `-- helper\n"));
+    assert!(stdout.ends_with("1 added, 2 removed, 1 modified\n"));
+
+    rtree_cmd()
+        .arg("--diff")
+        .arg(&old)
+        .arg(temp.path().join("missing"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("missing"));
+}
+
+#[test]
+fn compare_against_snapshot_reports_drift_in_exit_code() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("dist");
+    fs::create_dir_all(root.join("bin")).unwrap();
+    fs::write(root.join("bin/app"), "").unwrap();
+    let snapshot = temp.path().join("layout.json");
+
+    rtree_cmd()
+        .arg("--snapshot")
+        .arg(&snapshot)
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout("");
+    assert!(
+        fs::read_to_string(&snapshot)
+            .unwrap()
+            .contains("\"name\": \"app\"")
+    );
+
+    rtree_cmd()
+        .arg("--compare")
+        .arg(&snapshot)
+        .arg(&root)
+        .assert()
+        .code(0)
+        .stdout(predicate::str::ends_with(
+            "0 added, 0 removed, 0 modified\n",
+        ));
+
+    fs::remove_file(root.join("bin/app")).unwrap();
+    fs::write(root.join("README"), "").unwrap();
+    rtree_cmd()
+        .arg("--compare")
+        .arg(&snapshot)
+        .arg(&root)
+        .assert()
+        .code(1)
+        .stdout(concat!(
+            "dist -> dist\n",
+            "|-- README [+]\n",
+            "`-- bin/\n",
+            "    `-- app [-]\n",
+            "\n",
+            "1 added, 1 removed, 0 modified\n",
+        ));
+
+    fs::write(&snapshot, "not json").unwrap();
+    rtree_cmd()
+        .arg("--compare")
+        .arg(&snapshot)
+        .arg(&root)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Invalid tree"));
+}
+
+#[test]
+fn compare_repeats_the_snapshot_walk_in_any_order() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("dist");
+    fs::create_dir_all(root.join("z")).unwrap();
+    fs::create_dir_all(root.join("logs")).unwrap();
+    fs::write(root.join("a.txt"), "").unwrap();
+    fs::write(root.join("logs/build.log"), "").unwrap();
+    let snapshot = temp.path().join("layout.json");
+
+    rtree_cmd()
+        .args(["-I", "logs", "--snapshot"])
+        .arg(&snapshot)
+        .arg(&root)
+        .assert()
+        .success();
+
+    // Saved without --dirsfirst and compared with it; -I comes from the snapshot
+    rtree_cmd()
+        .args(["--dirsfirst", "--compare"])
+        .arg(&snapshot)
+        .arg(&root)
+        .assert()
+        .code(0)
+        .stdout(concat!(
+            "dist -> dist\n",
+            "|-- z/\n",
+            "`-- a.txt\n",
+            "\n",
+            "0 added, 0 removed, 0 modified\n",
+        ));
+
+    // Walk options cannot be changed when comparing
+    rtree_cmd()
+        .args(["-L", "1", "--compare"])
+        .arg(&snapshot)
+        .arg(&root)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn apply_scaffolds_a_layout_and_skips_existing_entries() {
+    let temp = TempDir::new().unwrap();
+    let target = temp.path().join("project");
+    let layout = concat!(
+        "project\n",
+        "├── Cargo.toml\n",
+        "└── src/\n",
+        "    └── main.rs\n",
+    );
+
+    rtree_cmd()
+        .args(["apply", "-"])
+        .arg(&target)
+        .arg("--dry-run")
+        .write_stdin(layout)
+        .assert()
+        .success()
+        .stdout("create Cargo.toml\ncreate src/\ncreate src/main.rs\n");
+    assert!(!target.exists());
+
+    fs::create_dir_all(&target).unwrap();
+    fs::write(target.join("Cargo.toml"), "[package]").unwrap();
+    rtree_cmd()
+        .args(["apply", "-"])
+        .arg(&target)
+        .write_stdin(layout)
+        .assert()
+        .success()
+        .stdout("exists Cargo.toml\ncreate src/\ncreate src/main.rs\n");
+    assert_eq!(
+        fs::read_to_string(target.join("Cargo.toml")).unwrap(),
+        "[package]"
+    );
+    assert!(target.join("src/main.rs").is_file());
+
+    rtree_cmd()
+        .args(["apply", "-"])
+        .arg(&target)
+        .write_stdin("|-- a\nstray\n")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("line 2"));
+}
+
+#[test]
+fn hash_shows_digests_and_finds_modified_files_against_a_snapshot() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("dist");
+    fs::create_dir_all(root.join("bin")).unwrap();
+    fs::write(root.join("bin/app"), "abc").unwrap();
+    let snapshot = temp.path().join("layout.json");
+
+    rtree_cmd()
+        .args(["--hash", "sha256", "--hash-dirs"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(
+                r"^dist\n`-- \[[0-9a-f]{12}\] bin/\n    `-- \[ba7816bf8f01\] app\n$",
+            )
+            .unwrap(),
+        );
+
+    rtree_cmd()
+        .args(["--hash", "sha256", "--snapshot"])
+        .arg(&snapshot)
+        .arg(&root)
+        .assert()
+        .success();
+    assert!(
+        fs::read_to_string(&snapshot).unwrap().contains(
+            "\"hex\": \"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\""
+        )
+    );
+
+    fs::write(root.join("bin/app"), "abd").unwrap();
+    rtree_cmd()
+        .args(["--hash", "sha256", "--compare"])
+        .arg(&snapshot)
+        .arg(&root)
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("`-- app [~]\n"))
+        .stdout(predicate::str::ends_with(
+            "0 added, 0 removed, 1 modified\n",
+        ));
+}
+
+#[test]
+fn duplicates_marks_copies_within_the_walk() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("assets");
+    fs::create_dir_all(root.join("icons/old")).unwrap();
+    fs::create_dir_all(root.join("cache")).unwrap();
+    fs::write(root.join("icons/logo.png"), "logo").unwrap();
+    fs::write(root.join("icons/old/logo.png"), "logo").unwrap();
+    fs::write(root.join("icons/old/logo-v1.png"), "LOGO").unwrap();
+    fs::write(root.join("cache/logo.png"), "logo").unwrap();
+
+    rtree_cmd()
+        .args(["--duplicates", "-I", "cache"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(concat!(
+            "assets\n",
+            "`-- icons/\n",
+            "    |-- logo.png [dup #1]\n",
+            "    `-- old/\n",
+            "        |-- logo-v1.png\n",
+            "        `-- logo.png [dup #1]\n",
+            "\n",
+            "1 duplicate group, 2 files, 4 bytes wasted\n",
+        ));
+}
+
+#[test]
+fn snapshot_records_active_filters() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("dist");
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("app"), "binary").unwrap();
+    let snapshot = temp.path().join("layout.json");
+
+    rtree_cmd()
+        .args(["--min-size", "1K", "--type", "f,l", "--prune", "--snapshot"])
+        .arg(&snapshot)
+        .arg(&root)
+        .assert()
+        .success();
+
+    let json = fs::read_to_string(&snapshot).unwrap();
+    assert!(json.contains("\"min_size\": 1024"));
+    assert!(json.contains("\"prune\": true"));
+    assert!(json.contains("\"file\",\n      \"symlink\""));
+}